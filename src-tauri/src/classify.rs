@@ -0,0 +1,341 @@
+use serde::{Deserialize, Serialize};
+
+use crate::commands::{fold_text, parse_section, ProcessSettings, VideoInfo};
+use crate::summary::{build_summary_provider, StructuredSummary};
+
+// ─── Taxonomy ──────────────────────────────────────────────────────────────────
+
+/// A category and the words that suggest it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CategoryRule {
+    pub name: String,
+    /// Matched as substrings of the title and summary, ignoring case and
+    /// accents, e.g. `"programaci"` or `"paso a paso"`.
+    #[serde(default)]
+    pub keywords: Vec<String>,
+}
+
+/// Categories videos are filed under. Rules are tried in order, so more
+/// specific categories should come first.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct CategoryTaxonomy {
+    pub categories: Vec<CategoryRule>,
+    /// Used when nothing matches.
+    pub fallback: String,
+}
+
+impl Default for CategoryTaxonomy {
+    fn default() -> Self {
+        let rule = |name: &str, keywords: &[&str]| CategoryRule {
+            name: name.to_string(),
+            keywords: keywords.iter().map(|k| k.to_string()).collect(),
+        };
+        CategoryTaxonomy {
+            categories: vec![
+                rule(
+                    "Tutorial",
+                    &["tutorial", "cómo", "paso a paso", "aprende a"],
+                ),
+                rule(
+                    "Tecnología",
+                    &[
+                        "tecnolog",
+                        "software",
+                        "programaci",
+                        "inteligencia artificial",
+                    ],
+                ),
+                rule("Música", &["música", "canción", "song"]),
+                rule("Deportes", &["deport", "fútbol", "fitness"]),
+                rule("Salud", &["salud", "medicina", "nutrici"]),
+                rule("Noticias", &["noticia", "política", "economía"]),
+                rule(
+                    "Educativo",
+                    &["educaci", "ciencia", "historia", "universidad"],
+                ),
+                rule("Entretenimiento", &["entreteni", "humor", "vlog", "comedy"]),
+            ],
+            fallback: "Otros".to_string(),
+        }
+    }
+}
+
+impl CategoryTaxonomy {
+    /// Every category a video can end up in, fallback included.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .categories
+            .iter()
+            .map(|c| c.name.trim().to_string())
+            .filter(|n| !n.is_empty())
+            .collect();
+        let fallback = self.fallback.trim();
+        if !fallback.is_empty() && !names.iter().any(|n| n == fallback) {
+            names.push(fallback.to_string());
+        }
+        names
+    }
+
+    /// The category named in `text` (ignoring case and accents), preferring
+    /// the longest name so "Salud mental" wins over "Salud".
+    pub fn find_name(&self, text: &str) -> Option<String> {
+        let text = fold_text(text);
+        self.names()
+            .into_iter()
+            .filter(|name| text.contains(&fold_text(name)))
+            .max_by_key(|name| name.chars().count())
+    }
+
+    /// First category whose keywords appear in `text`, ignoring case and accents.
+    pub fn match_keywords(&self, text: &str) -> Option<String> {
+        let text = fold_text(text);
+        self.categories
+            .iter()
+            .find(|c| {
+                c.keywords
+                    .iter()
+                    .map(|k| fold_text(k.trim()))
+                    .any(|k| !k.is_empty() && text.contains(&k))
+            })
+            .map(|c| c.name.clone())
+    }
+}
+
+// ─── Classification ────────────────────────────────────────────────────────────
+
+/// Category assigned to a video and how it was decided.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Classification {
+    pub category: String,
+    /// The model's confidence between 0 and 1, when requested.
+    pub confidence: Option<f32>,
+    /// "summary", "keywords", "model" or "fallback".
+    pub method: String,
+    pub cost_usd: f64,
+}
+
+/// Summary characters considered by the keyword heuristics.
+const KEYWORD_SCAN_CHARS: usize = 500;
+/// Transcript characters sent to the model when there is no summary.
+const CLASSIFY_TRANSCRIPT_CHARS: usize = 6000;
+const CLASSIFY_MAX_TOKENS: u32 = 200;
+
+/// Classify without calling a model: the category the summary names, then
+/// keyword matching on the title and start of the summary, then the fallback.
+pub fn classify_offline(
+    taxonomy: &CategoryTaxonomy,
+    title: &str,
+    summary: &str,
+    structured: Option<&StructuredSummary>,
+) -> Classification {
+    let classification = |category: String, method: &str| Classification {
+        category,
+        confidence: None,
+        method: method.to_string(),
+        cost_usd: 0.0,
+    };
+
+    let named = match structured {
+        Some(fields) => fields.category.clone(),
+        None => parse_section(summary, "Categoría"),
+    };
+    if let Some(category) = taxonomy.find_name(&named) {
+        return classification(category, "summary");
+    }
+
+    let start: String = summary.chars().take(KEYWORD_SCAN_CHARS).collect();
+    match taxonomy.match_keywords(&format!("{} {}", title, start)) {
+        Some(category) => classification(category, "keywords"),
+        None => classification(taxonomy.fallback.clone(), "fallback"),
+    }
+}
+
+/// Ask the summary model for a category out of `taxonomy`.
+async fn classify_with_model(
+    taxonomy: &CategoryTaxonomy,
+    video_info: &VideoInfo,
+    content: &str,
+    settings: &ProcessSettings,
+    include_confidence: bool,
+) -> Result<Classification, String> {
+    let provider = build_summary_provider(
+        settings.summary_provider.as_ref(),
+        &settings.anthropic_api_key,
+        &settings.summary_model,
+    )?;
+
+    let names = taxonomy.names();
+    let hints: Vec<String> = taxonomy
+        .categories
+        .iter()
+        .map(|c| {
+            if c.keywords.is_empty() {
+                format!("- {}", c.name)
+            } else {
+                format!("- {} (p. ej.: {})", c.name, c.keywords.join(", "))
+            }
+        })
+        .collect();
+    let system = format!(
+        "Clasifica el video en exactamente una de estas categorías:\n{}\n\
+         Si ninguna encaja, usa \"{}\".",
+        hints.join("\n"),
+        taxonomy.fallback
+    );
+
+    let mut properties = serde_json::json!({
+        "category": { "type": "string", "enum": names }
+    });
+    let mut required = vec!["category"];
+    if include_confidence {
+        properties["confidence"] = serde_json::json!({
+            "type": "number",
+            "description": "Confianza en la categoría elegida, entre 0 y 1."
+        });
+        required.push("confidence");
+    }
+    let schema = serde_json::json!({
+        "type": "object",
+        "additionalProperties": false,
+        "required": required,
+        "properties": properties
+    });
+
+    let user_content = format!(
+        "Video: \"{}\"\nCanal: {}\nDescripción: {}\n\n{}",
+        video_info.title,
+        video_info.channel,
+        video_info.description.as_deref().unwrap_or(""),
+        content
+    );
+    let reply = provider
+        .complete_structured(&system, &user_content, &schema, CLASSIFY_MAX_TOKENS)
+        .await?;
+    let json: serde_json::Value = serde_json::from_str(&reply.text)
+        .map_err(|e| format!("El modelo devolvió una clasificación inválida: {}", e))?;
+
+    let answer = json["category"].as_str().unwrap_or_default();
+    let category = taxonomy
+        .find_name(answer)
+        .unwrap_or_else(|| taxonomy.fallback.clone());
+    Ok(Classification {
+        category,
+        confidence: json["confidence"]
+            .as_f64()
+            .filter(|_| include_confidence)
+            .map(|c| c.clamp(0.0, 1.0) as f32),
+        method: "model".to_string(),
+        cost_usd: provider.reply_cost(&reply),
+    })
+}
+
+// ─── Commands ──────────────────────────────────────────────────────────────────
+
+/// Classify a video into the configured taxonomy. Offline heuristics by
+/// default; with `use_model` the summary model decides and can report its
+/// confidence.
+#[tauri::command]
+pub async fn classify_video(
+    video_info: VideoInfo,
+    summary: Option<String>,
+    transcript: Option<String>,
+    settings: ProcessSettings,
+    use_model: Option<bool>,
+    include_confidence: Option<bool>,
+) -> Result<Classification, String> {
+    let taxonomy = settings.category_taxonomy.clone().unwrap_or_default();
+    if taxonomy.names().is_empty() {
+        return Err("La taxonomía de categorías está vacía.".to_string());
+    }
+    let summary = summary.unwrap_or_default();
+
+    if !use_model.unwrap_or(false) {
+        return Ok(classify_offline(
+            &taxonomy,
+            &video_info.title,
+            &summary,
+            None,
+        ));
+    }
+
+    let content = if !summary.trim().is_empty() {
+        format!("Resumen:\n{}", summary)
+    } else {
+        let excerpt: String = transcript
+            .unwrap_or_default()
+            .chars()
+            .take(CLASSIFY_TRANSCRIPT_CHARS)
+            .collect();
+        format!("Inicio de la transcripción:\n{}", excerpt)
+    };
+    classify_with_model(
+        &taxonomy,
+        &video_info,
+        &content,
+        &settings,
+        include_confidence.unwrap_or(false),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn match_keywords_ignores_case_and_accents_on_both_sides() {
+        let taxonomy = CategoryTaxonomy::default();
+        assert_eq!(
+            taxonomy
+                .match_keywords("Historia de la MUSICA andina")
+                .as_deref(),
+            Some("Música")
+        );
+        assert_eq!(
+            taxonomy
+                .match_keywords("Introducción a la Programación")
+                .as_deref(),
+            Some("Tecnología")
+        );
+        assert_eq!(taxonomy.match_keywords("Vacaciones en la playa"), None);
+    }
+
+    #[test]
+    fn classify_offline_prefers_the_category_named_by_the_summary() {
+        let taxonomy = CategoryTaxonomy::default();
+        let structured = StructuredSummary {
+            category: "salud".to_string(),
+            ..Default::default()
+        };
+        let result = classify_offline(&taxonomy, "Tutorial de Rust", "", Some(&structured));
+        assert_eq!(
+            (result.category.as_str(), result.method.as_str()),
+            ("Salud", "summary")
+        );
+
+        let summary = "## 🏷 Categoría\nDeportes\n";
+        let result = classify_offline(&taxonomy, "Tutorial de Rust", summary, None);
+        assert_eq!(
+            (result.category.as_str(), result.method.as_str()),
+            ("Deportes", "summary")
+        );
+    }
+
+    #[test]
+    fn classify_offline_falls_back_to_keywords_then_the_fallback() {
+        let taxonomy = CategoryTaxonomy::default();
+        let summary = "## 🏷 Categoría\nJardinería\n";
+        let result = classify_offline(&taxonomy, "Cómo podar rosales", summary, None);
+        assert_eq!(
+            (result.category.as_str(), result.method.as_str()),
+            ("Tutorial", "keywords")
+        );
+
+        let result = classify_offline(&taxonomy, "Vacaciones en la playa", summary, None);
+        assert_eq!(
+            (result.category.as_str(), result.method.as_str()),
+            ("Otros", "fallback")
+        );
+    }
+}
@@ -5,6 +5,27 @@ use tauri::{AppHandle, Emitter, Manager};
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
 
+use crate::classify::{classify_offline, CategoryTaxonomy};
+use crate::embeddings::EmbeddingProviderConfig;
+use crate::library::{index_result, record_result};
+use crate::notion::{
+    append_blocks, build_properties, count_requests, delete_blocks, effective_mapping,
+    find_pages_by_url, inspect_database, markdown_to_blocks, next_version, page_block_ids,
+    page_cover, send_with_retry, split_first_batch, update_page, video_block,
+    NotionExistingPagePolicy, NotionFieldValues, NotionPropertyMapping, NotionVideoBlock,
+    QueryError, NOTION_API_URL, NOTION_VERSION,
+};
+use crate::obsidian::{write_obsidian_note, ObsidianExport};
+use crate::summary::{build_summary_provider, StructuredSummary, SummaryProviderConfig};
+use crate::tags::{video_tags, TagDictionary};
+use crate::templates::{
+    check_selected_template, render_markdown, selected_template, MarkdownTemplates,
+    SummarySections, TemplateContext, TemplateSegment, DEFAULT_MARKDOWN_TEMPLATE,
+};
+use crate::transcription::{
+    build_transcription_provider, TranscriptionProvider, TranscriptionProviderConfig,
+};
+
 // ─── Data Types ────────────────────────────────────────────────────────────────
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -12,6 +33,9 @@ pub struct ProgressEvent {
     pub stage: String,
     pub message: String,
     pub percent: Option<f32>,
+    /// Queue job the event belongs to; `None` outside the job queue.
+    #[serde(default)]
+    pub job_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -20,11 +44,31 @@ pub struct VideoInfo {
     pub channel: String,
     pub duration: u64,
     pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub thumbnail: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub upload_date: Option<String>, // ISO format: "YYYY-MM-DD"
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TranscriptSegment {
+    pub start: f64, // seconds
+    pub end: f64,
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Transcript {
+    pub text: String,
+    pub language: Option<String>,
+    pub segments: Vec<TranscriptSegment>,
+    /// What the transcription cost; zero for subtitles and local models.
+    #[serde(default)]
+    pub cost_usd: f64,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ProcessResult {
     pub video_info: VideoInfo,
@@ -32,10 +76,20 @@ pub struct ProcessResult {
     pub summary: String,
     pub tokens_used: u32,
     pub audio_duration_seconds: f32,
+    /// Transcription plus summary.
     pub cost_estimate: f64,
+    #[serde(default)]
+    pub transcription_cost_usd: f64,
+    pub saved_path: Option<String>,
+    pub notion_url: Option<String>,
+    pub language: Option<String>,
+    pub segments: Vec<TranscriptSegment>,
+    /// Row of this video in the local library, once stored.
+    #[serde(default)]
+    pub library_id: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct ProcessSettings {
     pub groq_api_key: String,
     pub anthropic_api_key: String,
@@ -46,6 +100,70 @@ pub struct ProcessSettings {
     pub custom_prompt: String,
     pub save_locally: bool,
     pub send_to_notion: bool,
+    #[serde(default)]
+    pub transcription_language: Option<String>,
+    #[serde(default)]
+    pub hierarchical_summary: Option<bool>,
+    /// Try the video's own subtitles before paying for transcription (default: on).
+    #[serde(default)]
+    pub use_captions: Option<bool>,
+    /// Subtitle languages in order of preference, e.g. `["es", "en"]`.
+    #[serde(default)]
+    pub caption_languages: Vec<String>,
+    /// Accept YouTube's machine-translated captions when the video has no
+    /// subtitles in its own language among `caption_languages` (default: off).
+    #[serde(default)]
+    pub translated_captions: Option<bool>,
+    /// Speech-to-text backend; Groq when unset.
+    #[serde(default)]
+    pub transcription_provider: Option<TranscriptionProviderConfig>,
+    /// LLM backend for summaries; Anthropic when unset.
+    #[serde(default)]
+    pub summary_provider: Option<SummaryProviderConfig>,
+    /// Database columns each exported field is written to; the template's
+    /// Spanish columns when empty.
+    #[serde(default)]
+    pub notion_property_mapping: Vec<NotionPropertyMapping>,
+    /// What to do when the database already has a page for the video (default: overwrite).
+    #[serde(default)]
+    pub notion_existing_page: Option<NotionExistingPagePolicy>,
+    /// Embed the video or a bookmark to it at the top of the Notion page.
+    #[serde(default)]
+    pub notion_video_block: Option<NotionVideoBlock>,
+    /// Canonical tags and their synonyms, applied to keywords before exporting.
+    #[serde(default)]
+    pub tag_dictionary: TagDictionary,
+    /// Categories, their keywords and the fallback; the built-in list when unset.
+    #[serde(default)]
+    pub category_taxonomy: Option<CategoryTaxonomy>,
+    /// Save each Markdown file in a subfolder named after its category.
+    #[serde(default)]
+    pub markdown_category_folders: Option<bool>,
+    /// User-defined Markdown templates (MiniJinja), by name.
+    #[serde(default)]
+    pub markdown_templates: MarkdownTemplates,
+    /// Name of the template used for this job; the built-in layout when unset.
+    #[serde(default)]
+    pub markdown_template: Option<String>,
+    /// Save notes into an Obsidian vault instead of plain Markdown files.
+    #[serde(default)]
+    pub obsidian_export: Option<ObsidianExport>,
+    /// Ask the model for typed summary fields instead of free-form Markdown.
+    #[serde(default)]
+    pub structured_summary: Option<bool>,
+    /// Embeddings for semantic search; processed videos are not indexed when unset.
+    #[serde(default)]
+    pub embedding_provider: Option<EmbeddingProviderConfig>,
+}
+
+/// Settings without keys or exports, for tests to override field by field.
+#[cfg(test)]
+pub(crate) fn test_settings() -> ProcessSettings {
+    ProcessSettings {
+        summary_model: "claude-sonnet-4-6".to_string(),
+        save_locally: true,
+        ..Default::default()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -74,20 +192,98 @@ pub struct SummaryResult {
     pub output_tokens: u32,
     pub total_tokens: u32,
     pub cost_usd: f64,
+    pub provider: String,
+    /// Typed fields, when the summary was requested in structured mode.
+    #[serde(default)]
+    pub structured: Option<StructuredSummary>,
+}
+
+/// One video listed in a playlist or channel.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SourceEntry {
+    pub id: String,
+    pub title: String,
+    pub url: String,
+    pub channel: Option<String>,
+    pub duration: Option<u64>,
+    pub upload_date: Option<String>, // ISO format: "YYYY-MM-DD"
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SourceListing {
+    pub title: String,
+    pub is_playlist: bool,
+    pub entries: Vec<SourceEntry>,
+}
+
+/// Filters applied by `expand_source`. Entries whose date or duration is
+/// unknown are kept, since flat listings do not always include them.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SourceFilters {
+    #[serde(default)]
+    pub date_from: Option<String>, // "YYYY-MM-DD", inclusive
+    #[serde(default)]
+    pub date_to: Option<String>,
+    #[serde(default)]
+    pub max_count: Option<usize>,
+    #[serde(default)]
+    pub min_duration: Option<u64>, // seconds
+    #[serde(default)]
+    pub max_duration: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BatchItemResult {
+    pub url: String,
+    pub result: Option<ProcessResult>,
+    pub error: Option<String>,
 }
 
+// ─── Constants ─────────────────────────────────────────────────────────────────
+
+/// Upper bound on the size of each chunk when splitting long audio.
+const CHUNK_TARGET_BYTES: u64 = 20 * 1_048_576;
+/// Audio shared between consecutive chunks so no word is cut at a seam.
+const CHUNK_OVERLAP_SECONDS: f64 = 5.0;
+/// Upper bound of repeated words searched for when stitching chunks.
+const SEAM_MAX_WORDS: usize = 40;
+/// Length of each timestamped transcript paragraph in exports.
+const TRANSCRIPT_PARAGRAPH_SECONDS: f64 = 60.0;
+/// Caption languages tried when the user has not configured any.
+const DEFAULT_CAPTION_LANGUAGES: [&str; 2] = ["es", "en"];
+
+/// Transcripts estimated above this size are summarized section by section.
+const SUMMARY_SINGLE_PASS_TOKENS: usize = 150_000;
+/// Token budget of each transcript section in hierarchical mode.
+const SUMMARY_SECTION_TOKENS: usize = 40_000;
+/// Characters per token used for estimates.
+const CHARS_PER_TOKEN: usize = 3;
+const SUMMARY_MAX_TOKENS: u32 = 4096;
+const SECTION_SUMMARY_MAX_TOKENS: u32 = 2048;
+
+const SECTION_SUMMARY_PROMPT: &str = "Eres un asistente experto en análisis de contenido. \
+Recibirás una sección de la transcripción de un video largo. Resúmela en Español de forma \
+detallada y fiel: conserva ideas, datos, nombres, ejemplos y citas textuales relevantes. \
+No añadas introducciones ni conclusiones; este resumen se combinará con el de las demás secciones.";
+
 // ─── Helper Functions ──────────────────────────────────────────────────────────
 
-fn emit_progress(app: &AppHandle, stage: &str, message: &str, percent: Option<f32>) {
+tokio::task_local! {
+    /// Id of the queue job whose pipeline runs in the current task.
+    pub(crate) static CURRENT_JOB: String;
+}
+
+pub(crate) fn emit_progress(app: &AppHandle, stage: &str, message: &str, percent: Option<f32>) {
     let event = ProgressEvent {
         stage: stage.to_string(),
         message: message.to_string(),
         percent,
+        job_id: CURRENT_JOB.try_with(|id| id.clone()).ok(),
     };
     let _ = app.emit("process-progress", event);
 }
 
-fn get_app_data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+pub(crate) fn get_app_data_dir(app: &AppHandle) -> Result<PathBuf, String> {
     app.path()
         .app_data_dir()
         .map_err(|e| format!("No se pudo obtener el directorio de datos: {}", e))
@@ -101,6 +297,30 @@ fn get_temp_audio_dir(app: &AppHandle) -> Result<PathBuf, String> {
     Ok(dir)
 }
 
+/// Delete everything in the temp audio dir created for `file_stem`: partial
+/// downloads, audio chunks and subtitle files.
+pub(crate) fn remove_temp_files(app: &AppHandle, file_stem: &str) {
+    let Ok(entries) = get_temp_audio_dir(app).and_then(|dir| {
+        std::fs::read_dir(dir).map_err(|e| format!("Error leyendo directorio: {}", e))
+    }) else {
+        return;
+    };
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        let matches = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.starts_with(file_stem));
+        if !matches {
+            continue;
+        }
+        if path.is_dir() {
+            let _ = std::fs::remove_dir_all(&path);
+        } else {
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+}
+
 fn parse_download_percent(line: &str) -> Option<f32> {
     let trimmed = line.trim();
     if let Some(pos) = trimmed.find('%') {
@@ -112,7 +332,7 @@ fn parse_download_percent(line: &str) -> Option<f32> {
     }
 }
 
-fn format_duration(seconds: u64) -> String {
+pub(crate) fn format_duration(seconds: u64) -> String {
     let h = seconds / 3600;
     let m = (seconds % 3600) / 60;
     let s = seconds % 60;
@@ -123,20 +343,8 @@ fn format_duration(seconds: u64) -> String {
     }
 }
 
-fn calculate_claude_cost(model: &str, input_tokens: u32, output_tokens: u32) -> f64 {
-    let (input_price, output_price) = match model {
-        m if m.contains("claude-opus-4") => (15.0, 75.0),
-        m if m.contains("claude-sonnet-4") => (3.0, 15.0),
-        m if m.contains("claude-haiku-4") => (0.80, 4.0),
-        _ => (3.0, 15.0),
-    };
-    let input_cost = (input_tokens as f64 / 1_000_000.0) * input_price;
-    let output_cost = (output_tokens as f64 / 1_000_000.0) * output_price;
-    (input_cost + output_cost * 100.0).round() / 100.0
-}
-
 /// Extract a section from a markdown-formatted summary by its header keyword.
-fn parse_section(text: &str, header_keyword: &str) -> String {
+pub(crate) fn parse_section(text: &str, header_keyword: &str) -> String {
     let mut in_section = false;
     let mut result: Vec<&str> = Vec::new();
     for line in text.lines() {
@@ -154,8 +362,17 @@ fn parse_section(text: &str, header_keyword: &str) -> String {
     result.join("\n").trim().to_string()
 }
 
+/// Render items as `• item` lines.
+pub(crate) fn bullet_list(items: &[String]) -> String {
+    items
+        .iter()
+        .map(|i| format!("• {}", i))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 /// Truncate text to fit Notion's 2000-char rich_text limit.
-fn truncate_notion(text: &str, max: usize) -> String {
+pub(crate) fn truncate_notion(text: &str, max: usize) -> String {
     let chars: Vec<char> = text.chars().collect();
     if chars.len() <= max {
         text.to_string()
@@ -164,79 +381,325 @@ fn truncate_notion(text: &str, max: usize) -> String {
     }
 }
 
-fn build_notion_blocks(summary: &str, transcript: &str) -> serde_json::Value {
-    // Render summary as proper Notion blocks respecting markdown structure
-    let summary_blocks: Vec<serde_json::Value> = summary
+/// Lowercase `text` and strip the diacritics common in Spanish, Portuguese and
+/// French, so "Educación" and "educacion" compare equal.
+pub(crate) fn fold_text(text: &str) -> String {
+    text.chars()
+        .flat_map(|c| c.to_lowercase())
+        .map(|c| match c {
+            'á' | 'à' | 'â' | 'ä' | 'ã' => 'a',
+            'é' | 'è' | 'ê' | 'ë' => 'e',
+            'í' | 'ì' | 'î' | 'ï' => 'i',
+            'ó' | 'ò' | 'ô' | 'ö' | 'õ' => 'o',
+            'ú' | 'ù' | 'û' | 'ü' => 'u',
+            'ñ' => 'n',
+            'ç' => 'c',
+            other => other,
+        })
+        .collect()
+}
+
+/// Format seconds as `MM:SS` or `H:MM:SS`.
+pub(crate) fn format_timestamp(seconds: f64) -> String {
+    let total = seconds.max(0.0) as u64;
+    let (h, m, s) = (total / 3600, (total % 3600) / 60, total % 60);
+    if h > 0 {
+        format!("{}:{:02}:{:02}", h, m, s)
+    } else {
+        format!("{:02}:{:02}", m, s)
+    }
+}
+
+/// YouTube id of a `watch?v=`, `youtu.be/`, `/shorts/`, `/embed/` or `/live/` link.
+pub(crate) fn youtube_id(url: &str) -> Option<String> {
+    let url = url.trim();
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let rest = rest.split('#').next().unwrap_or("");
+    let (location, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let (host, path) = location.split_once('/').unwrap_or((location, ""));
+    let host = host.to_lowercase();
+    let host = host
+        .trim_start_matches("www.")
+        .trim_start_matches("m.")
+        .trim_start_matches("music.");
+    let mut path = path.split('/').filter(|p| !p.is_empty());
+
+    let id = match (host, path.next()) {
+        ("youtu.be", id) => id,
+        ("youtube.com" | "youtube-nocookie.com", Some("watch")) => query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("v=")),
+        ("youtube.com" | "youtube-nocookie.com", Some("shorts" | "embed" | "live" | "v")) => {
+            path.next()
+        }
+        _ => None,
+    };
+    id.filter(|id| !id.is_empty()).map(|id| id.to_string())
+}
+
+/// Identity of a video across URL variants (`youtu.be`, `&t=`, `&list=`…):
+/// `youtube:<id>`, or the URL without fragment and trailing slash for other sites.
+pub(crate) fn video_key(url: &str) -> String {
+    match youtube_id(url) {
+        Some(id) => format!("youtube:{}", id),
+        None => url
+            .trim()
+            .split('#')
+            .next()
+            .unwrap_or("")
+            .trim_end_matches('/')
+            .to_string(),
+    }
+}
+
+/// Link to a moment of the video (`url&t=SECONDS`).
+pub(crate) fn timestamp_url(url: &str, seconds: f64) -> String {
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!("{}{}t={}", url, separator, seconds.max(0.0) as u64)
+}
+
+/// Merge consecutive segments into paragraphs spanning about `max_seconds` each.
+fn group_into_paragraphs(
+    segments: &[TranscriptSegment],
+    max_seconds: f64,
+) -> Vec<TranscriptSegment> {
+    let mut paragraphs: Vec<TranscriptSegment> = Vec::new();
+    for segment in segments {
+        match paragraphs.last_mut() {
+            Some(current) if segment.end - current.start <= max_seconds => {
+                current.text.push(' ');
+                current.text.push_str(&segment.text);
+                current.end = segment.end;
+            }
+            _ => paragraphs.push(segment.clone()),
+        }
+    }
+    paragraphs
+}
+
+/// Render segments as an SRT (`srt`) or WebVTT (`vtt`) subtitle file.
+fn format_subtitles(segments: &[TranscriptSegment], format: &str) -> String {
+    let stamp = |seconds: f64, separator: char| {
+        let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+        format!(
+            "{:02}:{:02}:{:02}{}{:03}",
+            millis / 3_600_000,
+            (millis % 3_600_000) / 60_000,
+            (millis % 60_000) / 1000,
+            separator,
+            millis % 1000
+        )
+    };
+
+    let mut out = String::new();
+    if format == "vtt" {
+        out.push_str("WEBVTT\n\n");
+    }
+    for (index, segment) in segments.iter().enumerate() {
+        if format == "vtt" {
+            out.push_str(&format!(
+                "{} --> {}\n{}\n\n",
+                stamp(segment.start, '.'),
+                stamp(segment.end, '.'),
+                segment.text
+            ));
+        } else {
+            out.push_str(&format!(
+                "{}\n{} --> {}\n{}\n\n",
+                index + 1,
+                stamp(segment.start, ','),
+                stamp(segment.end, ','),
+                segment.text
+            ));
+        }
+    }
+    out
+}
+
+/// Parse an SRT (`00:01:02,500`) or WebVTT (`01:02.500`) cue timestamp into seconds.
+fn parse_caption_timestamp(value: &str) -> Option<f64> {
+    let value = value.trim().replace(',', ".");
+    let mut seconds = 0.0;
+    for part in value.split(':') {
+        seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+    }
+    Some(seconds)
+}
+
+/// Parse an SRT or WebVTT subtitle file into transcript segments.
+///
+/// Inline tags are stripped, and lines repeated from the previous cue are
+/// dropped: YouTube's auto-generated captions scroll, so every cue starts
+/// with the line already shown by the one before.
+fn parse_captions(content: &str) -> Vec<TranscriptSegment> {
+    let mut segments: Vec<TranscriptSegment> = Vec::new();
+    let mut previous_lines: Vec<String> = Vec::new();
+    let mut lines = content.lines().map(|l| l.trim_end_matches('\r'));
+
+    while let Some(line) = lines.next() {
+        let Some((start, rest)) = line.split_once("-->") else {
+            continue;
+        };
+        // VTT cue settings ("align:start position:0%") follow the end time.
+        let end = rest.split_whitespace().next().unwrap_or("");
+        let (Some(start), Some(end)) = (parse_caption_timestamp(start), parse_caption_timestamp(end))
+        else {
+            continue;
+        };
+
+        let mut cue_lines: Vec<String> = Vec::new();
+        for text in lines.by_ref() {
+            if text.trim().is_empty() {
+                break;
+            }
+            let mut clean = String::new();
+            let mut in_tag = false;
+            for c in text.chars() {
+                match c {
+                    '<' => in_tag = true,
+                    '>' if in_tag => in_tag = false,
+                    _ if !in_tag => clean.push(c),
+                    _ => {}
+                }
+            }
+            let clean = clean
+                .replace("&nbsp;", " ")
+                .replace("&amp;", "&")
+                .replace("&lt;", "<")
+                .replace("&gt;", ">");
+            let clean = clean.split_whitespace().collect::<Vec<_>>().join(" ");
+            if !clean.is_empty() {
+                cue_lines.push(clean);
+            }
+        }
+
+        let new_lines: Vec<String> = cue_lines
+            .iter()
+            .filter(|l| !previous_lines.contains(l))
+            .cloned()
+            .collect();
+        if !cue_lines.is_empty() {
+            previous_lines = cue_lines;
+        }
+        if new_lines.is_empty() {
+            continue;
+        }
+
+        segments.push(TranscriptSegment {
+            start,
+            end,
+            text: new_lines.join(" "),
+        });
+    }
+    segments
+}
+
+/// Filesystem-safe version of a video title.
+fn safe_file_stem(title: &str) -> String {
+    let safe_title: String = title
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == ' ' || c == '-' { c } else { '_' })
+        .collect();
+    safe_title.trim().replace(' ', "_")
+}
+
+fn build_notion_blocks(
+    summary: &str,
+    transcript: &str,
+    segments: Option<&[TranscriptSegment]>,
+    video_info: &VideoInfo,
+    video: Option<NotionVideoBlock>,
+) -> Vec<serde_json::Value> {
+    let video_url = video_info.url.as_str();
+    // The default prompt bullets with "•", which Markdown does not treat as a list marker.
+    let summary_markdown = summary
         .lines()
-        .filter(|l| !l.trim().is_empty())
-        .map(|line| {
-            if line.starts_with("## ") {
-                let text = line.trim_start_matches('#').trim();
-                serde_json::json!({
-                    "object": "block",
-                    "type": "heading_2",
-                    "heading_2": {
-                        "rich_text": [{ "type": "text", "text": { "content": text } }]
-                    }
-                })
-            } else if line.starts_with("# ") {
-                let text = line.trim_start_matches('#').trim();
-                serde_json::json!({
-                    "object": "block",
-                    "type": "heading_1",
-                    "heading_1": {
-                        "rich_text": [{ "type": "text", "text": { "content": text } }]
-                    }
+        .map(|line| match line.trim_start().strip_prefix("• ") {
+            Some(item) => format!("- {}", item),
+            None => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let summary_blocks = markdown_to_blocks(&summary_markdown);
+
+    let transcript_chunks: Vec<serde_json::Value> = match segments {
+        // Timed transcript: one paragraph per minute, each opening with a link to that moment.
+        Some(segments) if !segments.is_empty() => {
+            group_into_paragraphs(segments, TRANSCRIPT_PARAGRAPH_SECONDS)
+                .iter()
+                .map(|paragraph| {
+                    let mut rich_text = vec![serde_json::json!({
+                        "type": "text",
+                        "text": {
+                            "content": format!("[{}] ", format_timestamp(paragraph.start)),
+                            "link": { "url": timestamp_url(video_url, paragraph.start) }
+                        }
+                    })];
+                    rich_text.extend(
+                        paragraph
+                            .text
+                            .chars()
+                            .collect::<Vec<char>>()
+                            .chunks(2000)
+                            .map(|chunk| {
+                                let text: String = chunk.iter().collect();
+                                serde_json::json!({ "type": "text", "text": { "content": text } })
+                            }),
+                    );
+                    serde_json::json!({
+                        "object": "block",
+                        "type": "paragraph",
+                        "paragraph": { "rich_text": rich_text }
+                    })
                 })
-            } else if line.starts_with("> ") {
-                let text = line.trim_start_matches('>').trim();
+                .collect()
+        }
+        _ => transcript
+            .chars()
+            .collect::<Vec<char>>()
+            .chunks(2000)
+            .map(|chunk| {
+                let text: String = chunk.iter().collect();
                 serde_json::json!({
                     "object": "block",
-                    "type": "quote",
-                    "quote": {
+                    "type": "paragraph",
+                    "paragraph": {
                         "rich_text": [{ "type": "text", "text": { "content": text } }]
                     }
                 })
-            } else if line.starts_with("• ") || line.starts_with("- ") || line.starts_with("* ") {
-                let clean = line.trim_start_matches(['•', '-', '*', ' ']).trim();
-                serde_json::json!({
-                    "object": "block",
-                    "type": "bulleted_list_item",
-                    "bulleted_list_item": {
-                        "rich_text": [{ "type": "text", "text": { "content": clean } }]
-                    }
-                })
-            } else if line.starts_with("---") {
-                serde_json::json!({ "object": "block", "type": "divider", "divider": {} })
-            } else {
+            })
+            .collect(),
+    };
+
+    let mut blocks: Vec<serde_json::Value> = Vec::new();
+    if let Some(kind) = video {
+        blocks.push(video_block(kind, video_url));
+    }
+    let description = video_info.description.as_deref().map(str::trim);
+    if let Some(description) = description.filter(|d| !d.is_empty()) {
+        let paragraphs: Vec<serde_json::Value> = description
+            .split("\n\n")
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .map(|p| {
                 serde_json::json!({
                     "object": "block",
                     "type": "paragraph",
                     "paragraph": {
-                        "rich_text": [{ "type": "text", "text": { "content": line } }]
+                        "rich_text": [{ "type": "text", "text": { "content": truncate_notion(p, 2000) } }]
                     }
                 })
-            }
-        })
-        .collect();
-
-    let transcript_chunks: Vec<serde_json::Value> = transcript
-        .chars()
-        .collect::<Vec<char>>()
-        .chunks(2000)
-        .map(|chunk| {
-            let text: String = chunk.iter().collect();
-            serde_json::json!({
-                "object": "block",
-                "type": "paragraph",
-                "paragraph": {
-                    "rich_text": [{ "type": "text", "text": { "content": text } }]
-                }
             })
-        })
-        .collect();
-
-    let mut blocks: Vec<serde_json::Value> = Vec::new();
+            .collect();
+        blocks.push(serde_json::json!({
+            "object": "block",
+            "type": "toggle",
+            "toggle": {
+                "rich_text": [{ "type": "text", "text": { "content": "📝 Descripción del video" } }],
+                "children": paragraphs
+            }
+        }));
+    }
     blocks.extend(summary_blocks);
     blocks.push(serde_json::json!({ "object": "block", "type": "divider", "divider": {} }));
     blocks.push(serde_json::json!({
@@ -248,7 +711,505 @@ fn build_notion_blocks(summary: &str, transcript: &str) -> serde_json::Value {
         }
     }));
 
-    serde_json::Value::Array(blocks)
+    blocks
+}
+
+/// Read the duration in seconds of an audio file from ffmpeg's stderr banner.
+async fn probe_audio_duration(audio_path: &str) -> Result<f64, String> {
+    let output = Command::new("ffmpeg")
+        .args(["-hide_banner", "-i", audio_path])
+        .output()
+        .await
+        .map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                "ffmpeg no está instalado y es necesario para dividir audios largos. Consulta SETUP.md."
+                    .to_string()
+            } else {
+                format!("Error ejecutando ffmpeg: {}", e)
+            }
+        })?;
+
+    // ffmpeg exits with an error when no output file is given; the banner is still printed.
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    parse_ffmpeg_duration(&stderr).ok_or_else(|| "No se pudo determinar la duración del audio.".to_string())
+}
+
+/// Parse `Duration: HH:MM:SS.xx` from ffmpeg output.
+fn parse_ffmpeg_duration(text: &str) -> Option<f64> {
+    let pos = text.find("Duration: ")?;
+    let rest = &text[pos + "Duration: ".len()..];
+    let value = rest.split(',').next()?.trim();
+    let mut parts = value.split(':');
+    let h: f64 = parts.next()?.parse().ok()?;
+    let m: f64 = parts.next()?.parse().ok()?;
+    let s: f64 = parts.next()?.parse().ok()?;
+    Some(h * 3600.0 + m * 60.0 + s)
+}
+
+/// Split the audio with ffmpeg into overlapping chunks below the provider's
+/// upload limit and transcribe them in order. Returns each chunk's start offset
+/// in seconds together with its raw response body.
+#[allow(clippy::too_many_arguments)]
+async fn transcribe_in_chunks(
+    app: &AppHandle,
+    provider: &dyn TranscriptionProvider,
+    audio_path: &str,
+    file_size: u64,
+    chunk_target_bytes: u64,
+    chunk_dir: &std::path::Path,
+    language: Option<&str>,
+    response_format: &str,
+) -> Result<Vec<(f64, String)>, String> {
+    let duration = probe_audio_duration(audio_path).await?;
+    if duration <= 0.0 {
+        return Err("No se pudo determinar la duración del audio.".to_string());
+    }
+
+    // Size the chunks from the average bitrate, leaving headroom for VBR peaks.
+    let bytes_per_second = file_size as f64 / duration;
+    let chunk_seconds = (chunk_target_bytes as f64 / bytes_per_second).floor();
+    if chunk_seconds <= CHUNK_OVERLAP_SECONDS * 2.0 {
+        return Err("El bitrate del audio es demasiado alto para dividirlo en fragmentos.".to_string());
+    }
+    let step = chunk_seconds - CHUNK_OVERLAP_SECONDS;
+    let total_chunks = ((duration - CHUNK_OVERLAP_SECONDS) / step).ceil().max(1.0) as usize;
+
+    tokio::fs::create_dir_all(chunk_dir)
+        .await
+        .map_err(|e| format!("Error creando directorio temporal: {}", e))?;
+
+    let mut parts: Vec<(f64, String)> = Vec::with_capacity(total_chunks);
+    for index in 0..total_chunks {
+        let start = index as f64 * step;
+        let chunk_path = chunk_dir
+            .join(format!("chunk_{:03}.mp3", index))
+            .to_string_lossy()
+            .to_string();
+
+        emit_progress(
+            app,
+            "transcribe",
+            &format!("Transcribiendo fragmento {} de {}...", index + 1, total_chunks),
+            Some(index as f32 / total_chunks as f32 * 100.0),
+        );
+
+        let status = Command::new("ffmpeg")
+            .args([
+                "-hide_banner",
+                "-loglevel",
+                "error",
+                "-y",
+                "-ss",
+                &format!("{:.3}", start),
+                "-t",
+                &format!("{:.3}", chunk_seconds),
+                "-i",
+                audio_path,
+                "-c",
+                "copy",
+                &chunk_path,
+            ])
+            .kill_on_drop(true)
+            .status()
+            .await
+            .map_err(|e| format!("Error ejecutando ffmpeg: {}", e))?;
+
+        if !status.success() {
+            return Err(format!("ffmpeg no pudo extraer el fragmento {}.", index + 1));
+        }
+
+        let body = provider
+            .transcribe_file(&chunk_path, language, response_format)
+            .await?;
+        parts.push((start, body));
+        let _ = tokio::fs::remove_file(&chunk_path).await;
+    }
+
+    Ok(parts)
+}
+
+/// Transcribe an audio file with the given provider, chunking it when it
+/// exceeds the provider's upload limit. Returns `(offset_seconds, body)` for
+/// every request made.
+async fn run_transcription(
+    app: &AppHandle,
+    provider: &dyn TranscriptionProvider,
+    audio_path: &str,
+    language: Option<&str>,
+    response_format: &str,
+) -> Result<Vec<(f64, String)>, String> {
+    let file_size = tokio::fs::metadata(audio_path)
+        .await
+        .map_err(|e| format!("Error leyendo archivo de audio: {}", e))?
+        .len();
+
+    let limit = match provider.max_upload_bytes() {
+        Some(limit) if file_size > limit => limit,
+        _ => {
+            emit_progress(
+                app,
+                "transcribe",
+                &format!("Transcribiendo audio con {}...", provider.name()),
+                None,
+            );
+            let body = provider
+                .transcribe_file(audio_path, language, response_format)
+                .await?;
+            return Ok(vec![(0.0, body)]);
+        }
+    };
+
+    emit_progress(
+        app,
+        "transcribe",
+        &format!(
+            "El audio ({:.1} MB) excede el límite de {:.0} MB de {}. Dividiendo en fragmentos...",
+            file_size as f64 / 1_048_576.0,
+            limit as f64 / 1_048_576.0,
+            provider.name()
+        ),
+        None,
+    );
+
+    let chunk_dir = PathBuf::from(format!("{}_chunks", audio_path));
+    let result = transcribe_in_chunks(
+        app,
+        provider,
+        audio_path,
+        file_size,
+        CHUNK_TARGET_BYTES.min(limit / 5 * 4),
+        &chunk_dir,
+        language,
+        response_format,
+    )
+    .await;
+    let _ = tokio::fs::remove_dir_all(&chunk_dir).await;
+    result
+}
+
+/// Language names Whisper reports in `verbose_json`, with their BCP-47 tags.
+const WHISPER_LANGUAGES: &[(&str, &str)] = &[
+    ("english", "en"),
+    ("chinese", "zh"),
+    ("german", "de"),
+    ("spanish", "es"),
+    ("russian", "ru"),
+    ("korean", "ko"),
+    ("french", "fr"),
+    ("japanese", "ja"),
+    ("portuguese", "pt"),
+    ("turkish", "tr"),
+    ("polish", "pl"),
+    ("catalan", "ca"),
+    ("dutch", "nl"),
+    ("arabic", "ar"),
+    ("swedish", "sv"),
+    ("italian", "it"),
+    ("indonesian", "id"),
+    ("hindi", "hi"),
+    ("finnish", "fi"),
+    ("vietnamese", "vi"),
+    ("hebrew", "he"),
+    ("ukrainian", "uk"),
+    ("greek", "el"),
+    ("malay", "ms"),
+    ("czech", "cs"),
+    ("romanian", "ro"),
+    ("danish", "da"),
+    ("hungarian", "hu"),
+    ("tamil", "ta"),
+    ("norwegian", "no"),
+    ("thai", "th"),
+    ("urdu", "ur"),
+    ("croatian", "hr"),
+    ("bulgarian", "bg"),
+    ("lithuanian", "lt"),
+    ("latin", "la"),
+    ("maori", "mi"),
+    ("malayalam", "ml"),
+    ("welsh", "cy"),
+    ("slovak", "sk"),
+    ("telugu", "te"),
+    ("persian", "fa"),
+    ("latvian", "lv"),
+    ("bengali", "bn"),
+    ("serbian", "sr"),
+    ("azerbaijani", "az"),
+    ("slovenian", "sl"),
+    ("kannada", "kn"),
+    ("estonian", "et"),
+    ("macedonian", "mk"),
+    ("breton", "br"),
+    ("basque", "eu"),
+    ("icelandic", "is"),
+    ("armenian", "hy"),
+    ("nepali", "ne"),
+    ("mongolian", "mn"),
+    ("bosnian", "bs"),
+    ("kazakh", "kk"),
+    ("albanian", "sq"),
+    ("swahili", "sw"),
+    ("galician", "gl"),
+    ("marathi", "mr"),
+    ("punjabi", "pa"),
+    ("sinhala", "si"),
+    ("khmer", "km"),
+    ("shona", "sn"),
+    ("yoruba", "yo"),
+    ("somali", "so"),
+    ("afrikaans", "af"),
+    ("occitan", "oc"),
+    ("georgian", "ka"),
+    ("belarusian", "be"),
+    ("tajik", "tg"),
+    ("sindhi", "sd"),
+    ("gujarati", "gu"),
+    ("amharic", "am"),
+    ("yiddish", "yi"),
+    ("lao", "lo"),
+    ("uzbek", "uz"),
+    ("faroese", "fo"),
+    ("haitian creole", "ht"),
+    ("pashto", "ps"),
+    ("turkmen", "tk"),
+    ("nynorsk", "nn"),
+    ("maltese", "mt"),
+    ("sanskrit", "sa"),
+    ("luxembourgish", "lb"),
+    ("myanmar", "my"),
+    ("tibetan", "bo"),
+    ("tagalog", "tl"),
+    ("malagasy", "mg"),
+    ("assamese", "as"),
+    ("tatar", "tt"),
+    ("hawaiian", "haw"),
+    ("lingala", "ln"),
+    ("hausa", "ha"),
+    ("bashkir", "ba"),
+    ("javanese", "jv"),
+    ("sundanese", "su"),
+    ("cantonese", "yue"),
+];
+
+/// `language` as a BCP-47 tag (`es`, `es-419`). Whisper reports language
+/// names (`spanish`) while captions and other backends already use tags.
+pub(crate) fn language_tag(language: &str) -> String {
+    let language = language.trim();
+    WHISPER_LANGUAGES
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(language))
+        .map(|(_, tag)| tag.to_string())
+        .unwrap_or_else(|| language.to_string())
+}
+
+/// Build a `Transcript` from Whisper `verbose_json` bodies, shifting segment
+/// times by each chunk's offset and dropping segments repeated in the overlap.
+fn merge_verbose_transcripts(parts: &[(f64, String)]) -> Result<Transcript, String> {
+    let mut language = None;
+    let mut segments: Vec<TranscriptSegment> = Vec::new();
+
+    for (offset, body) in parts {
+        let json: serde_json::Value = serde_json::from_str(body)
+            .map_err(|e| format!("Error parseando la transcripción: {}", e))?;
+        if language.is_none() {
+            language = json["language"].as_str().map(language_tag);
+        }
+
+        let covered_until = segments.last().map(|s| s.end).unwrap_or(0.0);
+        for segment in json["segments"].as_array().into_iter().flatten() {
+            let text = segment["text"].as_str().unwrap_or("").trim();
+            let start = segment["start"].as_f64().unwrap_or(0.0) + offset;
+            let end = segment["end"].as_f64().unwrap_or(0.0) + offset;
+            // Segments that start inside audio already transcribed belong to the overlap.
+            if text.is_empty() || start + 0.5 < covered_until {
+                continue;
+            }
+            segments.push(TranscriptSegment {
+                start,
+                end,
+                text: text.to_string(),
+            });
+        }
+    }
+
+    let text = segments
+        .iter()
+        .map(|s| s.text.as_str())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Ok(Transcript {
+        text,
+        language,
+        segments,
+        cost_usd: 0.0,
+    })
+}
+
+/// Normalize a word for seam comparison: lowercase, no surrounding punctuation.
+fn seam_word(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase()
+}
+
+/// Join chunk transcripts, dropping the words repeated at each seam because of
+/// the audio overlap.
+fn stitch_transcripts(parts: &[String]) -> String {
+    let mut words: Vec<&str> = Vec::new();
+    for part in parts {
+        let next: Vec<&str> = part.split_whitespace().collect();
+        let max_overlap = SEAM_MAX_WORDS.min(words.len()).min(next.len());
+
+        // Longest suffix of what we have that reappears as a prefix of the next chunk;
+        // a single shared word is too likely to be a coincidence.
+        let overlap = (2..=max_overlap)
+            .rev()
+            .find(|&n| {
+                words[words.len() - n..]
+                    .iter()
+                    .zip(&next[..n])
+                    .all(|(a, b)| seam_word(a) == seam_word(b))
+            })
+            .unwrap_or(0);
+
+        words.extend_from_slice(&next[overlap..]);
+    }
+    words.join(" ")
+}
+
+/// Rough token count; deliberately pessimistic for Spanish text.
+fn estimate_tokens(text: &str) -> usize {
+    text.chars().count() / CHARS_PER_TOKEN
+}
+
+/// Split text into sections of at most `max_chars`, preferring sentence ends
+/// and falling back to whitespace.
+fn split_into_sections(text: &str, max_chars: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut sections = Vec::new();
+    let mut start = 0;
+
+    while start < chars.len() {
+        let mut end = (start + max_chars).min(chars.len());
+        if end < chars.len() {
+            let window = &chars[start..end];
+            let min_cut = window.len() / 2;
+            let sentence_end = window
+                .iter()
+                .enumerate()
+                .rev()
+                .take_while(|(i, _)| *i > min_cut)
+                .find(|(i, c)| {
+                    matches!(c, '.' | '?' | '!')
+                        && window.get(i + 1).is_some_and(|n| n.is_whitespace())
+                })
+                .map(|(i, _)| i + 1);
+            let space = window.iter().rposition(|c| c.is_whitespace()).filter(|&i| i > 0);
+            if let Some(cut) = sentence_end.or(space) {
+                end = start + cut;
+            }
+        }
+
+        let section: String = chars[start..end].iter().collect();
+        let section = section.trim();
+        if !section.is_empty() {
+            sections.push(section.to_string());
+        }
+        start = end;
+    }
+    sections
+}
+
+/// Convert yt-dlp's "YYYYMMDD" dates to "YYYY-MM-DD".
+fn iso_upload_date(value: &serde_json::Value) -> Option<String> {
+    value.as_str().and_then(|d| {
+        if d.len() == 8 && d.chars().all(|c| c.is_ascii_digit()) {
+            Some(format!("{}-{}-{}", &d[..4], &d[4..6], &d[6..8]))
+        } else {
+            None
+        }
+    })
+}
+
+/// Run `yt-dlp --flat-playlist -J` and parse its JSON output.
+async fn ytdlp_flat_listing(url: &str) -> Result<serde_json::Value, String> {
+    let output = Command::new("yt-dlp")
+        .args([
+            "--flat-playlist",
+            "-J",
+            // Channel tabs omit dates unless approximated from "3 weeks ago" labels.
+            "--extractor-args",
+            "youtubetab:approximate_date",
+            url,
+        ])
+        .output()
+        .await
+        .map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                "yt-dlp no está instalado. Consulta SETUP.md para instrucciones.".to_string()
+            } else {
+                format!("Error ejecutando yt-dlp: {}", e)
+            }
+        })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("yt-dlp falló: {}", stderr));
+    }
+
+    serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Error parseando listado: {}", e))
+}
+
+/// Build a `SourceEntry` from a flat-playlist entry (or a single video).
+fn parse_source_entry(
+    json: &serde_json::Value,
+    fallback_channel: Option<&str>,
+) -> Option<SourceEntry> {
+    let id = json["id"].as_str()?.to_string();
+    let url = [&json["webpage_url"], &json["url"]]
+        .iter()
+        .filter_map(|v| v.as_str())
+        .find(|u| u.starts_with("http"))
+        .map(|u| u.to_string())
+        .unwrap_or_else(|| format!("https://www.youtube.com/watch?v={}", id));
+
+    let upload_date = iso_upload_date(&json["upload_date"]).or_else(|| {
+        json["timestamp"]
+            .as_i64()
+            .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+            .map(|dt| dt.format("%Y-%m-%d").to_string())
+    });
+
+    Some(SourceEntry {
+        id,
+        title: json["title"].as_str().unwrap_or("Sin título").to_string(),
+        url,
+        channel: json["channel"]
+            .as_str()
+            .or_else(|| json["uploader"].as_str())
+            .or(fallback_channel)
+            .map(|c| c.to_string()),
+        duration: json["duration"].as_f64().map(|d| d.round() as u64),
+        upload_date,
+    })
+}
+
+fn matches_source_filters(entry: &SourceEntry, filters: &SourceFilters) -> bool {
+    let date_ok = match &entry.upload_date {
+        Some(date) => {
+            filters.date_from.as_ref().is_none_or(|from| date >= from)
+                && filters.date_to.as_ref().is_none_or(|to| date <= to)
+        }
+        None => true,
+    };
+    let duration_ok = match entry.duration {
+        Some(duration) => {
+            filters.min_duration.is_none_or(|min| duration >= min)
+                && filters.max_duration.is_none_or(|max| duration <= max)
+        }
+        None => true,
+    };
+    date_ok && duration_ok
 }
 
 // ─── Commands ─────────────────────────────────────────────────────────────────
@@ -257,6 +1218,7 @@ fn build_notion_blocks(summary: &str, transcript: &str) -> serde_json::Value {
 pub async fn get_video_info(url: String) -> Result<VideoInfo, String> {
     let output = Command::new("yt-dlp")
         .args(["--dump-json", "--no-playlist", &url])
+        .kill_on_drop(true)
         .output()
         .await
         .map_err(|e| {
@@ -275,14 +1237,7 @@ pub async fn get_video_info(url: String) -> Result<VideoInfo, String> {
     let json: serde_json::Value = serde_json::from_slice(&output.stdout)
         .map_err(|e| format!("Error parseando metadata: {}", e))?;
 
-    // Convert yt-dlp upload_date "YYYYMMDD" → "YYYY-MM-DD"
-    let upload_date = json["upload_date"].as_str().and_then(|d| {
-        if d.len() == 8 {
-            Some(format!("{}-{}-{}", &d[..4], &d[4..6], &d[6..8]))
-        } else {
-            None
-        }
-    });
+    let upload_date = iso_upload_date(&json["upload_date"]);
 
     Ok(VideoInfo {
         title: json["title"].as_str().unwrap_or("Sin título").to_string(),
@@ -301,14 +1256,105 @@ pub async fn get_video_info(url: String) -> Result<VideoInfo, String> {
     })
 }
 
+/// List the videos of a playlist or channel (or the single video behind `url`)
+/// without downloading anything, applying `filters`.
 #[tauri::command]
-pub async fn download_audio(app: AppHandle, url: String) -> Result<String, String> {
-    let audio_dir = get_temp_audio_dir(&app)?;
-    let output_template = audio_dir
-        .join("%(id)s.%(ext)s")
-        .to_string_lossy()
-        .to_string();
-
+pub async fn expand_source(
+    app: AppHandle,
+    url: String,
+    filters: Option<SourceFilters>,
+) -> Result<SourceListing, String> {
+    let filters = filters.unwrap_or_default();
+    emit_progress(&app, "expand", "Obteniendo lista de videos...", None);
+
+    let json = ytdlp_flat_listing(&url).await?;
+    let is_playlist = json["_type"].as_str() == Some("playlist");
+    let title = json["title"].as_str().unwrap_or("Sin título").to_string();
+
+    if !is_playlist {
+        let entry = parse_source_entry(&json, None)
+            .ok_or("yt-dlp no devolvió información del video.")?;
+        return Ok(SourceListing {
+            title,
+            is_playlist,
+            entries: vec![entry],
+        });
+    }
+
+    let channel = json["channel"]
+        .as_str()
+        .or_else(|| json["uploader"].as_str())
+        .map(|c| c.to_string());
+
+    let mut entries: Vec<SourceEntry> = Vec::new();
+    for item in json["entries"].as_array().into_iter().flatten() {
+        // A channel URL lists its tabs (Videos, Shorts, Live) as nested playlists.
+        if item["_type"].as_str() == Some("playlist")
+            || item["ie_key"].as_str() == Some("YoutubeTab")
+        {
+            let Some(tab_url) = item["url"].as_str() else {
+                continue;
+            };
+            emit_progress(
+                &app,
+                "expand",
+                &format!(
+                    "Obteniendo videos de \"{}\"...",
+                    item["title"].as_str().unwrap_or(tab_url)
+                ),
+                None,
+            );
+            let tab = ytdlp_flat_listing(tab_url).await?;
+            entries.extend(
+                tab["entries"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|e| parse_source_entry(e, channel.as_deref())),
+            );
+        } else if let Some(entry) = parse_source_entry(item, channel.as_deref()) {
+            entries.push(entry);
+        }
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    entries.retain(|e| seen.insert(e.id.clone()) && matches_source_filters(e, &filters));
+    if let Some(max) = filters.max_count {
+        entries.truncate(max);
+    }
+
+    emit_progress(
+        &app,
+        "expand",
+        &format!("{} videos encontrados.", entries.len()),
+        Some(100.0),
+    );
+    Ok(SourceListing {
+        title,
+        is_playlist,
+        entries,
+    })
+}
+
+#[tauri::command]
+pub async fn download_audio(app: AppHandle, url: String) -> Result<String, String> {
+    download_audio_as(app, url, uuid::Uuid::new_v4().simple().to_string()).await
+}
+
+/// Download the audio as `<file_stem>.mp3` in the temp audio dir. A unique stem
+/// per download keeps concurrent jobs from picking up each other's file, and
+/// lets a cancelled job find its partial files.
+pub(crate) async fn download_audio_as(
+    app: AppHandle,
+    url: String,
+    file_stem: String,
+) -> Result<String, String> {
+    let audio_dir = get_temp_audio_dir(&app)?;
+    let output_template = audio_dir
+        .join(format!("{}.%(ext)s", file_stem))
+        .to_string_lossy()
+        .to_string();
+
     emit_progress(&app, "download", "Iniciando descarga de audio...", Some(0.0));
 
     let check = Command::new("yt-dlp")
@@ -336,14 +1382,18 @@ pub async fn download_audio(app: AppHandle, url: String) -> Result<String, Strin
         ])
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
+        .kill_on_drop(true)
         .spawn()
         .map_err(|e| format!("Error iniciando descarga: {}", e))?;
 
     if let Some(stdout) = child.stdout.take() {
         let mut reader = BufReader::new(stdout).lines();
         let app_clone = app.clone();
+        // Spawned tasks don't inherit task-locals; carry the job id over so
+        // download progress stays attributed to its job.
+        let job_id = CURRENT_JOB.try_with(Clone::clone).ok();
 
-        tokio::spawn(async move {
+        let read_progress = async move {
             while let Ok(Some(line)) = reader.next_line().await {
                 if line.contains("[download]") && line.contains('%') {
                     if let Some(pct) = parse_download_percent(&line) {
@@ -358,7 +1408,11 @@ pub async fn download_audio(app: AppHandle, url: String) -> Result<String, Strin
                     emit_progress(&app_clone, "download", "Convirtiendo a MP3...", Some(95.0));
                 }
             }
-        });
+        };
+        match job_id {
+            Some(id) => tokio::spawn(CURRENT_JOB.scope(id, read_progress)),
+            None => tokio::spawn(read_progress),
+        };
     }
 
     let status = child
@@ -372,91 +1426,260 @@ pub async fn download_audio(app: AppHandle, url: String) -> Result<String, Strin
         );
     }
 
-    let mp3_file = std::fs::read_dir(&audio_dir)
-        .map_err(|e| format!("Error leyendo directorio: {}", e))?
-        .filter_map(|e| e.ok())
-        .filter(|e| e.path().extension().and_then(|s| s.to_str()) == Some("mp3"))
-        .max_by_key(|e| e.metadata().and_then(|m| m.modified()).ok())
-        .ok_or("No se encontró el archivo de audio descargado.")?;
+    let mp3_file = audio_dir.join(format!("{}.mp3", file_stem));
+    if !mp3_file.is_file() {
+        return Err("No se encontró el archivo de audio descargado.".to_string());
+    }
 
-    let path = mp3_file.path().to_string_lossy().to_string();
+    let path = mp3_file.to_string_lossy().to_string();
     emit_progress(&app, "download", "Audio descargado correctamente.", Some(100.0));
     Ok(path)
 }
 
+/// Fetch the video's uploaded or auto-generated subtitles with yt-dlp, in the
+/// first available of `languages`. Returns `None` when there are no usable
+/// captions, so the caller can fall back to audio transcription.
 #[tauri::command]
-pub async fn transcribe_audio(
+pub async fn fetch_captions(
     app: AppHandle,
-    audio_path: String,
-    groq_api_key: String,
-    language: Option<String>,
-) -> Result<String, String> {
-    emit_progress(&app, "transcribe", "Enviando audio a Groq Whisper...", None);
+    url: String,
+    languages: Vec<String>,
+    allow_translated: Option<bool>,
+) -> Result<Option<Transcript>, String> {
+    fetch_captions_as(
+        app,
+        url,
+        languages,
+        allow_translated.unwrap_or(false),
+        uuid::Uuid::new_v4().simple().to_string(),
+    )
+    .await
+}
+
+/// Whether a track key such as `es-419` or `en-orig` is in `language`.
+fn caption_language_matches(key: &str, language: &str) -> bool {
+    key == language || key.starts_with(&format!("{}-", language))
+}
+
+/// Pick the subtitle track to download out of the video's manual and
+/// automatic tracks, returning its key and whether it is automatic.
+///
+/// Uploaded subtitles come first, then the automatic track in the spoken
+/// language (`<lang>-orig`). YouTube machine-translates that track into every
+/// other language, so translated tracks are only used with `allow_translated`.
+fn pick_caption_track(
+    manual: &[String],
+    automatic: &[String],
+    languages: &[String],
+    allow_translated: bool,
+) -> Option<(String, bool)> {
+    let find = |tracks: &[String], keep: &dyn Fn(&str) -> bool| {
+        languages.iter().find_map(|l| {
+            tracks
+                .iter()
+                .find(|t| caption_language_matches(t, l) && keep(t))
+                .cloned()
+        })
+    };
+
+    if let Some(track) = find(manual, &|_| true) {
+        return Some((track, false));
+    }
+    // Without translations yt-dlp lists the spoken language alone, with no "-orig" key.
+    let has_orig = automatic.iter().any(|t| t.ends_with("-orig"));
+    let original = |t: &str| t.ends_with("-orig") || (!has_orig && automatic.len() == 1);
+    if let Some(track) = find(automatic, &original) {
+        return Some((track, true));
+    }
+    if allow_translated {
+        if let Some(track) = find(automatic, &|t| !t.ends_with("-orig")) {
+            return Some((track, true));
+        }
+    }
+    None
+}
 
-    let file_bytes = tokio::fs::read(&audio_path)
+/// Run yt-dlp with `args`, returning its stdout or its stderr as the error.
+async fn run_ytdlp(args: &[&str]) -> Result<Vec<u8>, String> {
+    let output = Command::new("yt-dlp")
+        .args(args)
+        .kill_on_drop(true)
+        .output()
         .await
-        .map_err(|e| format!("Error leyendo archivo de audio: {}", e))?;
+        .map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                "yt-dlp no está instalado. Consulta SETUP.md para instrucciones.".to_string()
+            } else {
+                format!("Error ejecutando yt-dlp: {}", e)
+            }
+        })?;
 
-    let file_size_mb = file_bytes.len() as f64 / 1_048_576.0;
-    if file_size_mb > 25.0 {
-        return Err(format!(
-            "El archivo de audio ({:.1} MB) excede el límite de 25 MB de Groq. Prueba con un video más corto.",
-            file_size_mb
-        ));
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("yt-dlp falló: {}", stderr.trim()));
     }
+    Ok(output.stdout)
+}
 
-    let filename = PathBuf::from(&audio_path)
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("audio.mp3")
-        .to_string();
+/// `fetch_captions` working in `<file_stem>_captions` inside the temp audio dir.
+pub(crate) async fn fetch_captions_as(
+    app: AppHandle,
+    url: String,
+    languages: Vec<String>,
+    allow_translated: bool,
+    file_stem: String,
+) -> Result<Option<Transcript>, String> {
+    let languages: Vec<String> = if languages.iter().any(|l| !l.trim().is_empty()) {
+        languages
+            .iter()
+            .map(|l| l.trim().to_string())
+            .filter(|l| !l.is_empty())
+            .collect()
+    } else {
+        DEFAULT_CAPTION_LANGUAGES.iter().map(|l| l.to_string()).collect()
+    };
 
-    let file_part = reqwest::multipart::Part::bytes(file_bytes)
-        .file_name(filename)
-        .mime_str("audio/mpeg")
-        .map_err(|e| format!("Error preparando archivo: {}", e))?;
+    emit_progress(&app, "captions", "Buscando subtítulos del video...", None);
 
-    let mut form = reqwest::multipart::Form::new()
-        .part("file", file_part)
-        .text("model", "whisper-large-v3")
-        .text("response_format", "text");
+    let stdout = run_ytdlp(&["--dump-json", "--skip-download", "--no-playlist", &url]).await?;
+    let json: serde_json::Value = serde_json::from_slice(&stdout)
+        .map_err(|e| format!("Error parseando respuesta de yt-dlp: {}", e))?;
+    let tracks = |field: &str| -> Vec<String> {
+        json[field]
+            .as_object()
+            .map(|tracks| tracks.keys().cloned().collect())
+            .unwrap_or_default()
+    };
+    let Some((track, automatic)) = pick_caption_track(
+        &tracks("subtitles"),
+        &tracks("automatic_captions"),
+        &languages,
+        allow_translated,
+    ) else {
+        emit_progress(&app, "captions", "El video no tiene subtítulos utilizables.", None);
+        return Ok(None);
+    };
 
-    if let Some(lang) = language {
-        form = form.text("language", lang);
+    let captions_dir = get_temp_audio_dir(&app)?.join(format!("{}_captions", file_stem));
+    std::fs::create_dir_all(&captions_dir)
+        .map_err(|e| format!("Error creando directorio temporal: {}", e))?;
+    let output_template = captions_dir
+        .join("%(id)s.%(ext)s")
+        .to_string_lossy()
+        .to_string();
+
+    let output = run_ytdlp(&[
+        "--skip-download",
+        if automatic { "--write-auto-subs" } else { "--write-subs" },
+        "--sub-langs",
+        &track,
+        "--sub-format",
+        "vtt/srt/best",
+        "--no-playlist",
+        "-o",
+        &output_template,
+        &url,
+    ])
+    .await;
+
+    // Files are named "<id>.<lang>.<ext>"; only the picked track was requested.
+    let found = output.map(|_| {
+        let path = std::fs::read_dir(&captions_dir)
+            .ok()?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .find(|p| matches!(p.extension().and_then(|s| s.to_str()), Some("vtt" | "srt")))?;
+        let content = std::fs::read_to_string(&path).ok()?;
+        let segments = parse_captions(&content);
+        // Captions made only of "[Música]"-style annotations are not a transcript.
+        let usable = segments
+            .iter()
+            .any(|s| !s.text.starts_with('[') && s.text.chars().any(|c| c.is_alphanumeric()));
+        if !usable {
+            return None;
+        }
+        let text = segments
+            .iter()
+            .map(|s| s.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+        Some(Transcript {
+            text,
+            language: Some(language_tag(track.trim_end_matches("-orig"))),
+            segments,
+            cost_usd: 0.0,
+        })
+    });
+    let _ = std::fs::remove_dir_all(&captions_dir);
+    let transcript = found?;
+
+    match &transcript {
+        Some(t) => emit_progress(
+            &app,
+            "captions",
+            &format!(
+                "Subtítulos encontrados ({}).",
+                t.language.as_deref().unwrap_or("?")
+            ),
+            Some(100.0),
+        ),
+        None => emit_progress(&app, "captions", "El video no tiene subtítulos utilizables.", None),
     }
+    Ok(transcript)
+}
 
-    let client = reqwest::Client::new();
-    let response = client
-        .post("https://api.groq.com/openai/v1/audio/transcriptions")
-        .bearer_auth(&groq_api_key)
-        .multipart(form)
-        .send()
-        .await
-        .map_err(|e| format!("Error conectando con Groq: {}", e))?;
+#[tauri::command]
+pub async fn transcribe_audio(
+    app: AppHandle,
+    audio_path: String,
+    groq_api_key: String,
+    language: Option<String>,
+    provider: Option<TranscriptionProviderConfig>,
+) -> Result<String, String> {
+    let provider = build_transcription_provider(provider.as_ref(), &groq_api_key)?;
+    let parts = run_transcription(
+        &app,
+        provider.as_ref(),
+        &audio_path,
+        language.as_deref(),
+        "text",
+    )
+    .await?;
+    let bodies: Vec<String> = parts.into_iter().map(|(_, body)| body).collect();
 
-    if !response.status().is_success() {
-        let status = response.status();
-        let body = response.text().await.unwrap_or_default();
-        let msg = if status.as_u16() == 401 {
-            "API key de Groq inválida. Verifica tu configuración en Ajustes.".to_string()
-        } else if status.as_u16() == 413 {
-            "El archivo de audio es demasiado grande para Groq.".to_string()
-        } else {
-            format!("Error de Groq ({}): {}", status, body)
-        };
-        return Err(msg);
-    }
+    emit_progress(&app, "transcribe", "Transcripción completada.", Some(100.0));
+    Ok(stitch_transcripts(&bodies))
+}
 
-    let transcript = response
-        .text()
-        .await
-        .map_err(|e| format!("Error leyendo respuesta de Groq: {}", e))?;
+/// Like `transcribe_audio`, but requests Whisper `verbose_json` and keeps the
+/// segment timings and detected language.
+#[tauri::command]
+pub async fn transcribe_audio_timestamped(
+    app: AppHandle,
+    audio_path: String,
+    groq_api_key: String,
+    language: Option<String>,
+    provider: Option<TranscriptionProviderConfig>,
+) -> Result<Transcript, String> {
+    let provider = build_transcription_provider(provider.as_ref(), &groq_api_key)?;
+    let parts = run_transcription(
+        &app,
+        provider.as_ref(),
+        &audio_path,
+        language.as_deref(),
+        "verbose_json",
+    )
+    .await?;
+    let mut transcript = merge_verbose_transcripts(&parts)?;
+    let audio_seconds = transcript.segments.last().map(|s| s.end).unwrap_or(0.0);
+    transcript.cost_usd = provider.cost_usd(audio_seconds);
 
     emit_progress(&app, "transcribe", "Transcripción completada.", Some(100.0));
-    Ok(transcript.trim().to_string())
+    Ok(transcript)
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn generate_summary(
     app: AppHandle,
     transcript: String,
@@ -464,82 +1687,169 @@ pub async fn generate_summary(
     anthropic_api_key: String,
     model: String,
     custom_prompt: String,
+    hierarchical: Option<bool>,
+    provider: Option<SummaryProviderConfig>,
+    structured: Option<bool>,
+    category_taxonomy: Option<CategoryTaxonomy>,
 ) -> Result<SummaryResult, String> {
-    emit_progress(&app, "summarize", "Generando resumen con Claude...", None);
+    let provider = build_summary_provider(provider.as_ref(), &anthropic_api_key, &model)?;
+    let header = format!(
+        "Video: \"{}\"\nCanal: {}\nDuración: {}",
+        video_info.title,
+        video_info.channel,
+        format_duration(video_info.duration)
+    );
+
+    // Hierarchical mode is used on demand or whenever the transcript would not fit.
+    let use_sections =
+        hierarchical.unwrap_or_else(|| estimate_tokens(&transcript) > SUMMARY_SINGLE_PASS_TOKENS);
+
+    let mut input_tokens = 0;
+    let mut output_tokens = 0;
+
+    let (source, source_label) = if use_sections {
+        let sections = split_into_sections(&transcript, SUMMARY_SECTION_TOKENS * CHARS_PER_TOKEN);
+        let total = sections.len();
+        let mut notes: Vec<String> = Vec::with_capacity(total);
+
+        for (index, section) in sections.iter().enumerate() {
+            emit_progress(
+                &app,
+                "summarize",
+                &format!("Resumiendo sección {} de {}...", index + 1, total),
+                Some(index as f32 / (total + 1) as f32 * 100.0),
+            );
+            let reply = provider
+                .complete(
+                    SECTION_SUMMARY_PROMPT,
+                    &format!(
+                        "{}\n\nSección {} de {} de la transcripción:\n{}",
+                        header,
+                        index + 1,
+                        total,
+                        section
+                    ),
+                    SECTION_SUMMARY_MAX_TOKENS,
+                )
+                .await?;
+            input_tokens += reply.input_tokens;
+            output_tokens += reply.output_tokens;
+            notes.push(format!("### Sección {} de {}\n\n{}", index + 1, total, reply.text));
+        }
 
+        emit_progress(
+            &app,
+            "summarize",
+            &format!("Generando síntesis final con {}...", provider.name()),
+            Some(total as f32 / (total + 1) as f32 * 100.0),
+        );
+        (notes.join("\n\n"), "Resúmenes parciales de la transcripción")
+    } else {
+        emit_progress(
+            &app,
+            "summarize",
+            &format!("Generando resumen con {}...", provider.name()),
+            None,
+        );
+        (transcript.clone(), "Transcripción")
+    };
+
+    let categories = category_taxonomy.unwrap_or_default().names();
     let system_prompt = custom_prompt
         .replace("{{video_title}}", &video_info.title)
         .replace("{{channel}}", &video_info.channel)
         .replace("{{duration}}", &format_duration(video_info.duration))
-        .replace("{{transcript}}", &transcript);
-
-    let request_body = serde_json::json!({
-        "model": model,
-        "max_tokens": 4096,
-        "system": system_prompt,
-        "messages": [
-            {
-                "role": "user",
-                "content": format!(
-                    "Video: \"{}\"\nCanal: {}\nDuración: {}\n\nTranscripción:\n{}",
-                    video_info.title,
-                    video_info.channel,
-                    format_duration(video_info.duration),
-                    transcript
-                )
-            }
-        ]
-    });
-
-    let client = reqwest::Client::new();
-    let response = client
-        .post("https://api.anthropic.com/v1/messages")
-        .header("x-api-key", &anthropic_api_key)
-        .header("anthropic-version", "2023-06-01")
-        .header("content-type", "application/json")
-        .json(&request_body)
-        .send()
-        .await
-        .map_err(|e| format!("Error conectando con Anthropic: {}", e))?;
-
-    if !response.status().is_success() {
-        let status = response.status();
-        let body = response.text().await.unwrap_or_default();
-        let msg = if status.as_u16() == 401 {
-            "API key de Anthropic inválida. Verifica tu configuración en Ajustes.".to_string()
-        } else {
-            format!("Error de Anthropic ({}): {}", status, body)
-        };
-        return Err(msg);
-    }
-
-    let json: serde_json::Value = response
-        .json()
-        .await
-        .map_err(|e| format!("Error parseando respuesta de Anthropic: {}", e))?;
-
-    let summary = json["content"][0]["text"]
-        .as_str()
-        .ok_or("Respuesta inesperada de Anthropic")?
-        .to_string();
+        .replace("{{transcript}}", &source)
+        .replace("{{categories}}", &categories.join(", "));
+
+    let user_content = format!("{}\n\n{}:\n{}", header, source_label, source);
+    let (reply, structured) = if structured.unwrap_or(false) {
+        let reply = provider
+            .complete_structured(
+                &system_prompt,
+                &user_content,
+                &StructuredSummary::schema(&categories),
+                SUMMARY_MAX_TOKENS,
+            )
+            .await?;
+        let fields: StructuredSummary = serde_json::from_str(&reply.text)
+            .map_err(|e| format!("El modelo devolvió un resumen estructurado inválido: {}", e))?;
+        (reply, Some(fields))
+    } else {
+        let reply = provider
+            .complete(&system_prompt, &user_content, SUMMARY_MAX_TOKENS)
+            .await?;
+        (reply, None)
+    };
+    input_tokens += reply.input_tokens;
+    output_tokens += reply.output_tokens;
 
-    let input_tokens = json["usage"]["input_tokens"].as_u64().unwrap_or(0) as u32;
-    let output_tokens = json["usage"]["output_tokens"].as_u64().unwrap_or(0) as u32;
     let total_tokens = input_tokens + output_tokens;
-    let cost = calculate_claude_cost(&model, input_tokens, output_tokens);
+    let cost = provider.cost_usd(input_tokens, output_tokens);
 
     emit_progress(&app, "summarize", "Resumen generado correctamente.", Some(100.0));
 
     Ok(SummaryResult {
-        summary,
+        summary: match &structured {
+            Some(fields) => fields.to_markdown(),
+            None => reply.text,
+        },
         input_tokens,
         output_tokens,
         total_tokens,
         cost_usd: cost,
+        provider: provider.name().to_string(),
+        structured,
     })
 }
 
+/// Message for an error status from a request against the database. `problems`
+/// are the mapping mismatches `inspect_database` found; they are only looked
+/// up for a 400, which almost always means the database does not match the mapping.
+fn notion_database_error(status: u16, body: String, problems: &[String]) -> String {
+    match status {
+        401 => "API key de Notion inválida.".to_string(),
+        404 => "Database ID no encontrado. Verifica que la base de datos está compartida \
+                con tu integración."
+            .to_string(),
+        400 if !problems.is_empty() => format!(
+            "La base de datos de Notion no coincide con el mapeo de propiedades:\n• {}",
+            problems.join("\n• ")
+        ),
+        400 => {
+            let detail = serde_json::from_str::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|j| j["message"].as_str().map(str::to_string))
+                .unwrap_or(body);
+            format!("Notion rechazó la petición: {}", detail)
+        }
+        _ => format!("Error de Notion ({}): {}", status, body),
+    }
+}
+
+/// `notion_database_error`, inspecting the database first when Notion answered 400.
+async fn database_error(
+    client: &reqwest::Client,
+    api_key: &str,
+    database_id: &str,
+    mapping: &[NotionPropertyMapping],
+    taxonomy: &CategoryTaxonomy,
+    status: u16,
+    body: String,
+) -> String {
+    let problems = if status == 400 {
+        inspect_database(client, api_key, database_id, mapping, &taxonomy.names())
+            .await
+            .map(|report| report.problems)
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    notion_database_error(status, body, &problems)
+}
+
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn send_to_notion(
     app: AppHandle,
     notion_api_key: String,
@@ -547,11 +1857,22 @@ pub async fn send_to_notion(
     video_info: VideoInfo,
     summary: String,
     transcript: String,
+    segments: Option<Vec<TranscriptSegment>>,
+    structured: Option<StructuredSummary>,
+    property_mapping: Option<Vec<NotionPropertyMapping>>,
+    existing_page: Option<NotionExistingPagePolicy>,
+    video_block: Option<NotionVideoBlock>,
+    tag_dictionary: Option<TagDictionary>,
+    category_taxonomy: Option<CategoryTaxonomy>,
 ) -> Result<String, String> {
     emit_progress(&app, "notion", "Enviando a Notion...", None);
 
-    // Parse summary sections to map to database columns
-    let resumen_text = {
+    // Map summary fields to database columns: typed fields when available,
+    // otherwise sections parsed out of the Markdown.
+    let resumen_text = if let Some(fields) = &structured {
+        let puntos = bullet_list(&fields.key_points);
+        truncate_notion(format!("{}\n\n{}", fields.central_idea, puntos).trim(), 2000)
+    } else {
         let puntos = parse_section(&summary, "Puntos Clave");
         let idea = parse_section(&summary, "Idea Central");
         let combined = if !idea.is_empty() && !puntos.is_empty() {
@@ -564,126 +1885,162 @@ pub async fn send_to_notion(
         truncate_notion(&combined, 2000)
     };
 
-    let acciones_text = {
+    let acciones_text = if let Some(fields) = &structured {
+        truncate_notion(&bullet_list(&fields.action_items), 2000)
+    } else {
         let acciones = parse_section(&summary, "Ideas Accionables");
         truncate_notion(if acciones.is_empty() { &summary } else { &acciones }, 2000)
     };
 
-    let keywords_text = {
-        // Try to find a Keywords section; fall back to extracting from title words
-        let kw = parse_section(&summary, "Keywords");
-        if !kw.is_empty() {
-            truncate_notion(&kw, 500)
-        } else {
-            // Generate basic keywords from video title words (>3 chars)
-            let words: Vec<&str> = video_info.title
-                .split_whitespace()
-                .filter(|w| w.len() > 3)
-                .take(6)
-                .collect();
-            words.join(", ")
-        }
-    };
+    let tags = video_tags(
+        &summary,
+        structured.as_ref(),
+        &video_info.title,
+        &tag_dictionary.unwrap_or_default(),
+    );
 
-    // Determine category: prefer Claude's classification from the summary,
-    // fall back to simple keyword matching on title + first 500 chars of summary.
-    let categoria = {
-        let valid = ["Tutorial", "Entretenimiento", "Educativo", "Música",
-                     "Deportes", "Tecnología", "Noticias", "Salud", "Otros"];
-        let from_summary = parse_section(&summary, "Categoría");
-        let found = valid.iter()
-            .find(|&&v| from_summary.contains(v))
-            .map(|&v| v.to_string());
-        found.unwrap_or_else(|| {
-            let text = format!(
-                "{} {}",
-                video_info.title,
-                &summary[..summary.len().min(500)]
-            ).to_lowercase();
-            if text.contains("tutorial") || text.contains("cómo") || text.contains("paso a paso") || text.contains("aprende a") {
-                "Tutorial"
-            } else if text.contains("tecnolog") || text.contains("software") || text.contains("programaci") || text.contains("inteligencia artificial") {
-                "Tecnología"
-            } else if text.contains("música") || text.contains("musica") || text.contains("canción") || text.contains("song") {
-                "Música"
-            } else if text.contains("deport") || text.contains("fútbol") || text.contains("futbol") || text.contains("fitness") {
-                "Deportes"
-            } else if text.contains("salud") || text.contains("medicina") || text.contains("nutrici") {
-                "Salud"
-            } else if text.contains("noticia") || text.contains("política") || text.contains("politica") || text.contains("economía") {
-                "Noticias"
-            } else if text.contains("educaci") || text.contains("ciencia") || text.contains("historia") || text.contains("universidad") {
-                "Educativo"
-            } else if text.contains("entreteni") || text.contains("humor") || text.contains("vlog") || text.contains("comedy") {
-                "Entretenimiento"
-            } else {
-                "Otros"
-            }.to_string()
-        })
+    // The model's category when it names one of the taxonomy, else keyword heuristics.
+    let taxonomy = category_taxonomy.unwrap_or_default();
+    let categoria =
+        classify_offline(&taxonomy, &video_info.title, &summary, structured.as_ref()).category;
+
+    let children = build_notion_blocks(
+        &summary,
+        &transcript,
+        segments.as_deref(),
+        &video_info,
+        video_block,
+    );
+    let cover = page_cover(video_info.thumbnail.as_deref());
+
+    let mut values = NotionFieldValues {
+        title: video_info.title.clone(),
+        url: video_info.url.clone(),
+        channel: video_info.channel.clone(),
+        summary: resumen_text,
+        action_items: acciones_text,
+        keywords: tags,
+        category: categoria,
+        upload_date: video_info.upload_date.clone(),
+        duration_seconds: video_info.duration,
+        processed_at: chrono::Local::now().to_rfc3339(),
     };
+    let mapping = effective_mapping(property_mapping.as_deref());
+    let client = reqwest::Client::new();
 
-    let children = build_notion_blocks(&summary, &transcript);
-
-    let mut properties = serde_json::json!({
-        "Title": {
-            "title": [{ "text": { "content": video_info.title } }]
-        },
-        "URL Video": {
-            "url": video_info.url
-        },
-        "Canal YouTube": {
-            "rich_text": [{ "text": { "content": video_info.channel } }]
-        },
-        "Resumen Video": {
-            "rich_text": [{ "text": { "content": resumen_text } }]
-        },
-        "Acciones_Aplicación": {
-            "rich_text": [{ "text": { "content": acciones_text } }]
-        },
-        "Keywords": {
-            "rich_text": [{ "text": { "content": keywords_text } }]
-        },
-        "Categoría": {
-            "select": { "name": categoria }
+    // Look for pages already exported for this video, keyed by the URL column.
+    let policy = existing_page.unwrap_or_default();
+    let existing = match find_pages_by_url(
+        &client,
+        &notion_api_key,
+        &parent_id,
+        &mapping,
+        &video_info.url,
+    )
+    .await
+    {
+        Ok(pages) => pages,
+        Err(QueryError::Status(status, body)) => {
+            return Err(database_error(
+                &client,
+                &notion_api_key,
+                &parent_id,
+                &mapping,
+                &taxonomy,
+                status,
+                body,
+            )
+            .await);
+        }
+        Err(QueryError::Request(e)) => {
+            return Err(format!(
+                "Error buscando páginas existentes en Notion: {}",
+                e
+            ));
+        }
+    };
+    if let Some(page) = existing.first() {
+        match policy {
+            NotionExistingPagePolicy::Skip => {
+                emit_progress(&app, "notion", "El vídeo ya estaba en Notion; se omite.", Some(100.0));
+                return Ok(page.url.clone());
+            }
+            NotionExistingPagePolicy::Overwrite => {
+                let mut fields = serde_json::json!({ "properties": build_properties(&mapping, &values)? });
+                if let Some(cover) = &cover {
+                    fields["cover"] = cover.clone();
+                }
+                update_page(&client, &notion_api_key, &page.id, &fields).await?;
+                emit_progress(&app, "notion", "Reemplazando el contenido de la página existente...", None);
+                // Upload the new content before removing the old one, so a
+                // failed upload never leaves the page empty.
+                let old_blocks = page_block_ids(&client, &notion_api_key, &page.id).await?;
+                let total_requests = count_requests(&children);
+                append_blocks(&app, &client, &notion_api_key, &page.id, children, 0, total_requests)
+                    .await
+                    .map_err(|e| {
+                        format!(
+                            "La página se actualizó ({}) pero no se pudo completar su contenido: {}",
+                            page.url, e
+                        )
+                    })?;
+                delete_blocks(&client, &notion_api_key, &old_blocks)
+                    .await
+                    .map_err(|e| {
+                        format!(
+                            "La página se actualizó ({}) pero conserva el contenido anterior: {}",
+                            page.url, e
+                        )
+                    })?;
+                emit_progress(&app, "notion", "Entrada actualizada en Notion.", Some(100.0));
+                return Ok(page.url.clone());
+            }
+            NotionExistingPagePolicy::NewVersion => {
+                values.title = format!("{} (versión {})", values.title, next_version(&existing));
+            }
         }
-    });
-
-    // Only set date if yt-dlp provided one
-    if let Some(date) = &video_info.upload_date {
-        properties["Fecha Video"] = serde_json::json!({
-            "date": { "start": date }
-        });
     }
 
-    let request_body = serde_json::json!({
+    let properties = build_properties(&mapping, &values)?;
+
+    // Long transcripts exceed Notion's 100-block limit: the page is created
+    // with the leading flat blocks and the rest is appended in batches.
+    let total_requests = 1 + count_requests(&children);
+    let (first_batch, remaining) = split_first_batch(children);
+
+    let mut request_body = serde_json::json!({
         "parent": { "database_id": parent_id },
         "icon": { "type": "emoji", "emoji": "🎬" },
         "properties": properties,
-        "children": children
+        "children": first_batch
     });
+    if let Some(cover) = cover {
+        request_body["cover"] = cover;
+    }
 
-    let client = reqwest::Client::new();
-    let response = client
-        .post("https://api.notion.com/v1/pages")
-        .bearer_auth(&notion_api_key)
-        .header("Notion-Version", "2022-06-28")
-        .header("Content-Type", "application/json")
-        .json(&request_body)
-        .send()
-        .await
-        .map_err(|e| format!("Error conectando con Notion: {}", e))?;
+    let response = send_with_retry(
+        client
+            .post(format!("{}/pages", NOTION_API_URL))
+            .bearer_auth(&notion_api_key)
+            .header("Notion-Version", NOTION_VERSION)
+            .header("Content-Type", "application/json")
+            .json(&request_body),
+    )
+    .await?;
 
     if !response.status().is_success() {
-        let status = response.status();
+        let status = response.status().as_u16();
         let body = response.text().await.unwrap_or_default();
-        let msg = if status.as_u16() == 401 {
-            "API key de Notion inválida.".to_string()
-        } else if status.as_u16() == 404 {
-            "Database ID no encontrado. Verifica que la base de datos está compartida con tu integración.".to_string()
-        } else {
-            format!("Error de Notion ({}): {}", status, body)
-        };
-        return Err(msg);
+        return Err(database_error(
+            &client,
+            &notion_api_key,
+            &parent_id,
+            &mapping,
+            &taxonomy,
+            status,
+            body,
+        )
+        .await);
     }
 
     let json: serde_json::Value = response
@@ -692,40 +2049,102 @@ pub async fn send_to_notion(
         .map_err(|e| format!("Error parseando respuesta de Notion: {}", e))?;
 
     let page_url = json["url"].as_str().unwrap_or("").to_string();
+    if !remaining.is_empty() {
+        let page_id = json["id"].as_str().ok_or("Respuesta inesperada de Notion")?;
+        append_blocks(
+            &app,
+            &client,
+            &notion_api_key,
+            page_id,
+            remaining,
+            1,
+            total_requests,
+        )
+        .await
+        .map_err(|e| {
+            format!(
+                "La página se creó ({}) pero no se pudo completar su contenido: {}",
+                page_url, e
+            )
+        })?;
+    }
     emit_progress(&app, "notion", "Entrada creada en Notion.", Some(100.0));
     Ok(page_url)
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn save_markdown(
     video_info: VideoInfo,
     summary: String,
     transcript: String,
     output_dir: String,
+    segments: Option<Vec<TranscriptSegment>>,
+    structured: Option<StructuredSummary>,
+    tag_dictionary: Option<TagDictionary>,
+    category_taxonomy: Option<CategoryTaxonomy>,
+    category_folders: Option<bool>,
+    template: Option<String>,
+    cost_usd: Option<f64>,
 ) -> Result<String, String> {
-    let safe_title: String = video_info
-        .title
-        .chars()
-        .map(|c| if c.is_alphanumeric() || c == ' ' || c == '-' { c } else { '_' })
-        .collect();
-    let safe_title = safe_title.trim().replace(' ', "_");
+    let safe_title = safe_file_stem(&video_info.title);
 
     let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S").to_string();
     let filename = format!("{}_{}.md", safe_title, timestamp);
 
-    let path = PathBuf::from(&output_dir).join(&filename);
+    let category = classify_offline(
+        &category_taxonomy.unwrap_or_default(),
+        &video_info.title,
+        &summary,
+        structured.as_ref(),
+    )
+    .category;
+
+    let mut dir = PathBuf::from(&output_dir);
+    if category_folders.unwrap_or(false) && !category.trim().is_empty() {
+        dir = dir.join(safe_file_stem(&category));
+        tokio::fs::create_dir_all(&dir)
+            .await
+            .map_err(|e| format!("Error creando la carpeta {}: {}", dir.display(), e))?;
+    }
+    let path = dir.join(&filename);
 
-    let content = format!(
-        "# {}\n\n**Canal:** {}  \n**URL:** {}  \n**Duración:** {}  \n**Procesado:** {}\n\n---\n\n## Resumen\n\n{}\n\n---\n\n## Transcripción completa\n\n{}\n",
-        video_info.title,
-        video_info.channel,
-        video_info.url,
-        format_duration(video_info.duration),
-        chrono::Local::now().format("%d/%m/%Y %H:%M"),
-        summary,
-        transcript
+    let tags = video_tags(
+        &summary,
+        structured.as_ref(),
+        &video_info.title,
+        &tag_dictionary.unwrap_or_default(),
     );
 
+    let segments = segments.unwrap_or_default();
+    let paragraphs = group_into_paragraphs(&segments, TRANSCRIPT_PARAGRAPH_SECONDS);
+    let now = chrono::Local::now();
+    let context = TemplateContext {
+        duration: format_duration(video_info.duration),
+        // Typed fields render the summary themselves.
+        summary: match &structured {
+            Some(fields) => fields.to_markdown(),
+            None => summary.clone(),
+        },
+        sections: SummarySections::new(&summary, structured.as_ref()),
+        transcript_markdown: markdown_transcript(&video_info.url, &transcript, Some(&segments)),
+        transcript,
+        segments: segments.iter().map(|s| TemplateSegment::new(&video_info.url, s)).collect(),
+        paragraphs: paragraphs.iter().map(|p| TemplateSegment::new(&video_info.url, p)).collect(),
+        front_matter: front_matter(&video_info, &category, &tags),
+        tags,
+        category,
+        cost_usd,
+        processed_at: now.format("%d/%m/%Y %H:%M").to_string(),
+        processed_at_iso: now.to_rfc3339(),
+        structured,
+        video: video_info,
+    };
+    let content = render_markdown(
+        template.as_deref().unwrap_or(DEFAULT_MARKDOWN_TEMPLATE),
+        &context,
+    )?;
+
     tokio::fs::write(&path, content)
         .await
         .map_err(|e| format!("Error guardando archivo: {}", e))?;
@@ -733,6 +2152,82 @@ pub async fn save_markdown(
     Ok(path.to_string_lossy().to_string())
 }
 
+/// Transcript as Markdown paragraphs. With segment timings, every paragraph
+/// links back to its moment in the video.
+pub(crate) fn markdown_transcript(
+    video_url: &str,
+    transcript: &str,
+    segments: Option<&[TranscriptSegment]>,
+) -> String {
+    match segments {
+        Some(segments) if !segments.is_empty() => {
+            group_into_paragraphs(segments, TRANSCRIPT_PARAGRAPH_SECONDS)
+                .iter()
+                .map(|p| {
+                    format!(
+                        "[{}]({}) {}",
+                        format_timestamp(p.start),
+                        timestamp_url(video_url, p.start),
+                        p.text
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        }
+        _ => transcript.to_string(),
+    }
+}
+
+/// YAML front matter with the video's metadata and keyword list.
+pub(crate) fn front_matter(video_info: &VideoInfo, category: &str, keywords: &[String]) -> String {
+    // JSON strings are valid YAML scalars and take care of quoting.
+    let quote = |s: &str| serde_json::to_string(s).unwrap_or_default();
+    let mut lines = vec![
+        "---".to_string(),
+        format!("title: {}", quote(&video_info.title)),
+        format!("channel: {}", quote(&video_info.channel)),
+        format!("url: {}", quote(&video_info.url)),
+    ];
+    if let Some(date) = &video_info.upload_date {
+        lines.push(format!("upload_date: {}", quote(date)));
+    }
+    lines.push(format!("category: {}", quote(category)));
+    if keywords.is_empty() {
+        lines.push("keywords: []".to_string());
+    } else {
+        lines.push("keywords:".to_string());
+        lines.extend(keywords.iter().map(|k| format!("  - {}", quote(k))));
+    }
+    lines.push("---".to_string());
+    lines.join("\n") + "\n\n"
+}
+
+/// Write the timed transcript as an `srt` or `vtt` subtitle file next to the Markdown exports.
+#[tauri::command]
+pub async fn save_subtitles(
+    video_info: VideoInfo,
+    transcript: Transcript,
+    output_dir: String,
+    format: String,
+) -> Result<String, String> {
+    let format = format.to_lowercase();
+    if format != "srt" && format != "vtt" {
+        return Err(format!("Formato de subtítulos no soportado: {}", format));
+    }
+    if transcript.segments.is_empty() {
+        return Err("La transcripción no tiene marcas de tiempo.".to_string());
+    }
+
+    let filename = format!("{}.{}", safe_file_stem(&video_info.title), format);
+    let path = PathBuf::from(&output_dir).join(&filename);
+
+    tokio::fs::write(&path, format_subtitles(&transcript.segments, &format))
+        .await
+        .map_err(|e| format!("Error guardando archivo: {}", e))?;
+
+    Ok(path.to_string_lossy().to_string())
+}
+
 #[tauri::command]
 pub async fn open_folder(path: String) -> Result<(), String> {
     #[cfg(target_os = "windows")]
@@ -796,3 +2291,495 @@ pub async fn get_default_output_dir() -> Result<String, String> {
 
     Ok(output_dir.to_string_lossy().to_string())
 }
+
+// ─── Pipeline ─────────────────────────────────────────────────────────────────
+
+/// The video's own subtitles, when enabled in `settings` and available.
+pub(crate) async fn find_captions(
+    app: &AppHandle,
+    url: &str,
+    settings: &ProcessSettings,
+    file_stem: &str,
+) -> Result<Option<Transcript>, String> {
+    if !settings.use_captions.unwrap_or(true) {
+        return Ok(None);
+    }
+    let mut languages = settings.caption_languages.clone();
+    if languages.is_empty() {
+        languages.extend(settings.transcription_language.clone());
+    }
+    fetch_captions_as(
+        app.clone(),
+        url.to_string(),
+        languages,
+        settings.translated_captions.unwrap_or(false),
+        file_stem.to_string(),
+    )
+    .await
+}
+
+/// Transcribe downloaded audio with the provider configured in `settings`.
+pub(crate) async fn transcribe_downloaded(
+    app: &AppHandle,
+    audio_path: &str,
+    settings: &ProcessSettings,
+) -> Result<Transcript, String> {
+    transcribe_audio_timestamped(
+        app.clone(),
+        audio_path.to_string(),
+        settings.groq_api_key.clone(),
+        settings.transcription_language.clone(),
+        settings.transcription_provider.clone(),
+    )
+    .await
+}
+
+pub(crate) async fn summarize_with_settings(
+    app: &AppHandle,
+    video_info: &VideoInfo,
+    transcript: &str,
+    settings: &ProcessSettings,
+) -> Result<SummaryResult, String> {
+    generate_summary(
+        app.clone(),
+        transcript.to_string(),
+        video_info.clone(),
+        settings.anthropic_api_key.clone(),
+        settings.summary_model.clone(),
+        settings.custom_prompt.clone(),
+        settings.hierarchical_summary,
+        settings.summary_provider.clone(),
+        settings.structured_summary,
+        settings.category_taxonomy.clone(),
+    )
+    .await
+}
+
+/// Save the Markdown file and/or create the Notion page, as enabled in
+/// `settings`. Returns `(saved_path, notion_url)`.
+pub(crate) async fn export_outputs(
+    app: &AppHandle,
+    settings: &ProcessSettings,
+    video_info: &VideoInfo,
+    summary: &SummaryResult,
+    timed: &Transcript,
+) -> Result<(Option<String>, Option<String>), String> {
+    let mut saved_path = None;
+    if settings.save_locally && settings.obsidian_export.is_some() {
+        emit_progress(app, "save", "Guardando nota en Obsidian...", None);
+        let path = write_obsidian_note(video_info, summary, timed, settings).await?;
+        emit_progress(app, "save", &format!("Guardado en: {}", path), Some(100.0));
+        saved_path = Some(path);
+    } else if settings.save_locally {
+        let output_dir = match settings.output_dir.clone().filter(|d| !d.trim().is_empty()) {
+            Some(dir) => dir,
+            None => get_default_output_dir().await?,
+        };
+        emit_progress(app, "save", "Guardando archivo Markdown...", None);
+        let path = save_markdown(
+            video_info.clone(),
+            summary.summary.clone(),
+            timed.text.clone(),
+            output_dir,
+            Some(timed.segments.clone()),
+            summary.structured.clone(),
+            Some(settings.tag_dictionary.clone()),
+            settings.category_taxonomy.clone(),
+            settings.markdown_category_folders,
+            selected_template(settings)?,
+            Some(summary.cost_usd),
+        )
+        .await?;
+        emit_progress(app, "save", &format!("Guardado en: {}", path), Some(100.0));
+        saved_path = Some(path);
+    }
+
+    // Notion failures are reported but do not discard the work already done.
+    let mut notion_url = None;
+    if settings.send_to_notion {
+        match (&settings.notion_api_key, &settings.notion_parent_id) {
+            (Some(key), Some(parent)) if !key.is_empty() && !parent.is_empty() => {
+                match send_to_notion(
+                    app.clone(),
+                    key.clone(),
+                    parent.clone(),
+                    video_info.clone(),
+                    summary.summary.clone(),
+                    timed.text.clone(),
+                    Some(timed.segments.clone()),
+                    summary.structured.clone(),
+                    Some(settings.notion_property_mapping.clone()),
+                    settings.notion_existing_page,
+                    settings.notion_video_block,
+                    Some(settings.tag_dictionary.clone()),
+                    settings.category_taxonomy.clone(),
+                )
+                .await
+                {
+                    Ok(page_url) => notion_url = Some(page_url),
+                    Err(e) => emit_progress(
+                        app,
+                        "notion",
+                        &format!("⚠ Error enviando a Notion: {}", e),
+                        None,
+                    ),
+                }
+            }
+            _ => emit_progress(
+                app,
+                "notion",
+                "⚠ Notion omitido: falta API key o Database ID en Ajustes.",
+                None,
+            ),
+        }
+    }
+
+    Ok((saved_path, notion_url))
+}
+
+pub(crate) fn build_process_result(
+    video_info: VideoInfo,
+    timed: Transcript,
+    summary: SummaryResult,
+    saved_path: Option<String>,
+    notion_url: Option<String>,
+) -> ProcessResult {
+    ProcessResult {
+        audio_duration_seconds: video_info.duration as f32,
+        video_info,
+        transcript: timed.text,
+        summary: summary.summary,
+        tokens_used: summary.total_tokens,
+        cost_estimate: summary.cost_usd + timed.cost_usd,
+        transcription_cost_usd: timed.cost_usd,
+        saved_path,
+        notion_url,
+        language: timed.language,
+        segments: timed.segments,
+        library_id: None,
+    }
+}
+
+/// Run the full pipeline for a single video: metadata → audio → transcript →
+/// summary → Markdown / Notion export. Temporary audio is always removed.
+#[tauri::command]
+pub async fn process_video(
+    app: AppHandle,
+    url: String,
+    settings: ProcessSettings,
+) -> Result<ProcessResult, String> {
+    check_selected_template(&settings)?;
+    emit_progress(&app, "info", "Obteniendo información del video...", None);
+    let video_info = get_video_info(url.clone()).await?;
+    emit_progress(
+        &app,
+        "info",
+        &format!(
+            "Video: \"{}\" ({})",
+            video_info.title,
+            format_duration(video_info.duration)
+        ),
+        Some(100.0),
+    );
+
+    // Existing subtitles are free; only download and transcribe audio without them.
+    let file_stem = uuid::Uuid::new_v4().simple().to_string();
+    let timed = match find_captions(&app, &url, &settings, &file_stem).await? {
+        Some(captions) => captions,
+        None => {
+            let audio_path = download_audio_as(app.clone(), url, file_stem).await?;
+            let timed = transcribe_downloaded(&app, &audio_path, &settings).await;
+            let _ = tokio::fs::remove_file(&audio_path).await;
+            timed?
+        }
+    };
+
+    let summary = summarize_with_settings(&app, &video_info, &timed.text, &settings).await?;
+    let (saved_path, notion_url) =
+        export_outputs(&app, &settings, &video_info, &summary, &timed).await?;
+
+    let mut result = build_process_result(video_info, timed, summary.clone(), saved_path, notion_url);
+    record_result(&app, &mut result, &summary).await;
+    index_result(&app, &settings, &result).await;
+
+    emit_progress(&app, "done", "Procesamiento completado.", Some(100.0));
+    Ok(result)
+}
+
+/// Run `process_video` for each URL in order. A failed video is recorded and
+/// the batch continues with the next one.
+#[tauri::command]
+pub async fn process_batch(
+    app: AppHandle,
+    urls: Vec<String>,
+    settings: ProcessSettings,
+) -> Result<Vec<BatchItemResult>, String> {
+    let total = urls.len();
+    let mut results = Vec::with_capacity(total);
+
+    for (index, url) in urls.into_iter().enumerate() {
+        emit_progress(
+            &app,
+            "batch",
+            &format!("Procesando video {} de {}...", index + 1, total),
+            Some(index as f32 / total as f32 * 100.0),
+        );
+        match process_video(app.clone(), url.clone(), settings.clone()).await {
+            Ok(result) => results.push(BatchItemResult {
+                url,
+                result: Some(result),
+                error: None,
+            }),
+            Err(e) => {
+                emit_progress(&app, "batch", &format!("⚠ {}: {}", url, e), None);
+                results.push(BatchItemResult {
+                    url,
+                    result: None,
+                    error: Some(e),
+                });
+            }
+        }
+    }
+
+    let failed = results.iter().filter(|r| r.error.is_some()).count();
+    emit_progress(
+        &app,
+        "batch",
+        &format!(
+            "Lote completado: {} procesados, {} con error.",
+            total - failed,
+            failed
+        ),
+        Some(100.0),
+    );
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejected_lookup_reports_the_mapping_problems() {
+        let body = r#"{"object":"error","status":400,"message":"Could not find property"}"#;
+        let problems = ["Falta la columna \"URL\".".to_string()];
+        let msg = notion_database_error(400, body.to_string(), &problems);
+        assert!(msg.contains("no coincide con el mapeo"));
+        assert!(msg.contains("Falta la columna \"URL\"."));
+
+        // Without a mismatch to point at, Notion's own message is shown.
+        let msg = notion_database_error(400, body.to_string(), &[]);
+        assert_eq!(msg, "Notion rechazó la petición: Could not find property");
+        assert_eq!(
+            notion_database_error(401, String::new(), &[]),
+            "API key de Notion inválida."
+        );
+    }
+
+    fn segment(start: f64, end: f64, text: &str) -> TranscriptSegment {
+        TranscriptSegment {
+            start,
+            end,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn stitch_transcripts_drops_repeated_seam_words() {
+        let parts = vec![
+            "hola a todos, hoy vamos a hablar de Rust".to_string(),
+            "hablar de rust y de su compilador".to_string(),
+        ];
+        assert_eq!(
+            stitch_transcripts(&parts),
+            "hola a todos, hoy vamos a hablar de Rust y de su compilador"
+        );
+    }
+
+    #[test]
+    fn stitch_transcripts_keeps_single_shared_word() {
+        let parts = vec!["uno dos tres".to_string(), "tres cuatro".to_string()];
+        assert_eq!(stitch_transcripts(&parts), "uno dos tres tres cuatro");
+    }
+
+    #[test]
+    fn parse_ffmpeg_duration_reads_banner() {
+        let banner = "Input #0, mp3, from 'a.mp3':\n  \
+                      Duration: 01:02:03.50, start: 0.000000, bitrate: 128 kb/s";
+        assert_eq!(parse_ffmpeg_duration(banner), Some(3723.5));
+        assert_eq!(parse_ffmpeg_duration("Duration: N/A, bitrate: N/A"), None);
+        assert_eq!(parse_ffmpeg_duration("sin duración"), None);
+    }
+
+    #[test]
+    fn split_into_sections_prefers_sentence_ends() {
+        let text = "Primera frase larga aquí. Segunda frase algo más larga. Tercera.";
+        let sections = split_into_sections(text, 40);
+        assert_eq!(sections[0], "Primera frase larga aquí.");
+        assert!(sections.iter().all(|s| s.chars().count() <= 40));
+        assert_eq!(sections.join(" "), text);
+    }
+
+    #[test]
+    fn split_into_sections_falls_back_to_whitespace() {
+        let sections = split_into_sections("uno dos tres cuatro cinco", 10);
+        assert_eq!(sections, vec!["uno dos", "tres", "cuatro", "cinco"]);
+        assert!(split_into_sections("   ", 10).is_empty());
+    }
+
+    #[test]
+    fn language_tag_turns_whisper_names_into_tags() {
+        assert_eq!(language_tag("spanish"), "es");
+        assert_eq!(language_tag("Haitian Creole"), "ht");
+        assert_eq!(language_tag("es-419"), "es-419");
+        assert_eq!(language_tag("en"), "en");
+    }
+
+    #[test]
+    fn merge_verbose_transcripts_offsets_and_drops_overlap() {
+        let parts = vec![
+            (
+                0.0,
+                r#"{"language":"spanish","segments":[
+                    {"start":0.0,"end":4.0,"text":" Hola "},
+                    {"start":4.0,"end":9.0,"text":"mundo"}]}"#
+                    .to_string(),
+            ),
+            (
+                5.0,
+                r#"{"language":"english","segments":[
+                    {"start":0.0,"end":4.0,"text":"mundo"},
+                    {"start":4.0,"end":6.0,"text":""},
+                    {"start":4.5,"end":7.0,"text":"adiós"}]}"#
+                    .to_string(),
+            ),
+        ];
+        let transcript = merge_verbose_transcripts(&parts).unwrap();
+        assert_eq!(transcript.language.as_deref(), Some("es"));
+        assert_eq!(transcript.text, "Hola mundo adiós");
+        assert_eq!(transcript.segments.len(), 3);
+        assert_eq!(transcript.segments[2].start, 9.5);
+        assert_eq!(transcript.segments[2].end, 12.0);
+        assert!(merge_verbose_transcripts(&[(0.0, "no json".to_string())]).is_err());
+    }
+
+    #[test]
+    fn format_subtitles_writes_srt_and_vtt() {
+        let segments = vec![segment(1.5, 3723.25, "Hola"), segment(3723.25, 3725.0, "Adiós")];
+        assert_eq!(
+            format_subtitles(&segments, "srt"),
+            "1\n00:00:01,500 --> 01:02:03,250\nHola\n\n2\n01:02:03,250 --> 01:02:05,000\nAdiós\n\n"
+        );
+        assert_eq!(
+            format_subtitles(&segments[..1], "vtt"),
+            "WEBVTT\n\n00:00:01.500 --> 01:02:03.250\nHola\n\n"
+        );
+    }
+
+    #[test]
+    fn parse_caption_timestamp_accepts_srt_and_vtt() {
+        assert_eq!(parse_caption_timestamp("00:01:02,500"), Some(62.5));
+        assert_eq!(parse_caption_timestamp(" 01:02.250 "), Some(62.25));
+        assert_eq!(parse_caption_timestamp("1:00:00.000"), Some(3600.0));
+        assert_eq!(parse_caption_timestamp("aa:bb"), None);
+    }
+
+    #[test]
+    fn parse_captions_strips_tags_and_scrolling_repeats() {
+        let vtt = "WEBVTT\nKind: captions\n\n\
+                   00:00:00.000 --> 00:00:02.000 align:start position:0%\n\
+                   hola<00:00:01.000><c> a todos</c>\n\n\
+                   00:00:02.000 --> 00:00:04.000 align:start position:0%\n\
+                   hola a todos\n\
+                   bienvenidos &amp; gracias\n\n\
+                   00:00:04.000 --> 00:00:04.010\n\
+                   bienvenidos &amp; gracias\n\n";
+        let segments = parse_captions(vtt);
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].text, "hola a todos");
+        assert_eq!(segments[1].text, "bienvenidos & gracias");
+        assert_eq!(segments[1].start, 2.0);
+
+        let srt = "1\r\n00:00:01,000 --> 00:00:02,500\r\n<i>Hola</i>\r\n\r\n";
+        let segments = parse_captions(srt);
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].text, "Hola");
+        assert_eq!(segments[0].end, 2.5);
+    }
+
+    #[test]
+    fn pick_caption_track_prefers_manual_then_original() {
+        let keys = |keys: &[&str]| keys.iter().map(|k| k.to_string()).collect::<Vec<_>>();
+        let languages = keys(&["es", "en"]);
+        let automatic = keys(&["en-orig", "en", "es", "fr"]);
+
+        // Uploaded subtitles win, in preference order.
+        assert_eq!(
+            pick_caption_track(&keys(&["en", "es-419"]), &automatic, &languages, false),
+            Some(("es-419".to_string(), false))
+        );
+        // The Spanish automatic track is a translation; the original English one is used.
+        assert_eq!(
+            pick_caption_track(&[], &automatic, &languages, false),
+            Some(("en-orig".to_string(), true))
+        );
+        // Translated tracks only on request.
+        assert_eq!(
+            pick_caption_track(&[], &keys(&["de-orig", "de", "es"]), &languages, false),
+            None
+        );
+        assert_eq!(
+            pick_caption_track(&[], &keys(&["de-orig", "de", "es"]), &languages, true),
+            Some(("es".to_string(), true))
+        );
+        // A lone automatic track is in the spoken language.
+        assert_eq!(
+            pick_caption_track(&[], &keys(&["es"]), &languages, false),
+            Some(("es".to_string(), true))
+        );
+    }
+
+    #[test]
+    fn video_key_ignores_url_variants() {
+        let key = "youtube:dQw4w9WgXcQ";
+        for url in [
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+            "https://youtube.com/watch?v=dQw4w9WgXcQ&t=42s",
+            "https://www.youtube.com/watch?list=PL123&v=dQw4w9WgXcQ&index=2",
+            "https://youtu.be/dQw4w9WgXcQ?si=abc",
+            "https://m.youtube.com/shorts/dQw4w9WgXcQ",
+            "https://www.youtube.com/embed/dQw4w9WgXcQ",
+            " https://music.youtube.com/watch?v=dQw4w9WgXcQ#comments ",
+        ] {
+            assert_eq!(video_key(url), key, "{}", url);
+        }
+        assert_eq!(video_key("https://vimeo.com/123/#t=5"), "https://vimeo.com/123");
+        assert_eq!(video_key("https://www.youtube.com/@canal"), "https://www.youtube.com/@canal");
+    }
+
+    #[test]
+    fn matches_source_filters_keeps_unknown_values() {
+        let entry = |date: Option<&str>, duration: Option<u64>| SourceEntry {
+            id: "id".to_string(),
+            title: "t".to_string(),
+            url: "u".to_string(),
+            channel: None,
+            duration,
+            upload_date: date.map(|d| d.to_string()),
+        };
+        let filters = SourceFilters {
+            date_from: Some("2024-01-01".to_string()),
+            date_to: Some("2024-12-31".to_string()),
+            min_duration: Some(60),
+            max_duration: Some(600),
+            ..Default::default()
+        };
+        assert!(matches_source_filters(&entry(Some("2024-01-01"), Some(60)), &filters));
+        assert!(matches_source_filters(&entry(Some("2024-12-31"), Some(600)), &filters));
+        assert!(matches_source_filters(&entry(None, None), &filters));
+        assert!(!matches_source_filters(&entry(Some("2023-12-31"), Some(120)), &filters));
+        assert!(!matches_source_filters(&entry(Some("2025-01-01"), None), &filters));
+        assert!(!matches_source_filters(&entry(None, Some(59)), &filters));
+        assert!(!matches_source_filters(&entry(None, Some(601)), &filters));
+    }
+}
@@ -0,0 +1,385 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::commands::{Transcript, TranscriptSegment};
+
+// ─── Configuration ─────────────────────────────────────────────────────────────
+
+/// Embedding backend used for semantic search. Semantic indexing is off
+/// while no provider is configured.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EmbeddingProviderConfig {
+    /// Local model served by Ollama, e.g. `nomic-embed-text` or `bge-m3`.
+    Ollama {
+        /// Defaults to `http://localhost:11434`.
+        #[serde(default)]
+        base_url: Option<String>,
+        model: String,
+    },
+    /// Any server exposing `POST {base_url}/embeddings` (OpenAI, LocalAI,
+    /// llama.cpp server…).
+    OpenaiCompatible {
+        base_url: String,
+        #[serde(default)]
+        api_key: Option<String>,
+        model: String,
+    },
+}
+
+const OLLAMA_BASE_URL: &str = "http://localhost:11434";
+/// Texts sent per embeddings request.
+const EMBEDDING_BATCH_SIZE: usize = 32;
+/// Target length of a transcript chunk, in seconds of video.
+const CHUNK_SECONDS: f64 = 45.0;
+/// Hard cap on chunk length so untimed transcripts still split sensibly.
+const CHUNK_MAX_CHARS: usize = 1500;
+
+// ─── Provider Trait ────────────────────────────────────────────────────────────
+
+/// A model turning text into vectors comparable by cosine similarity.
+#[async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+    /// Model name, stored with every vector so a model change triggers re-indexing.
+    fn model(&self) -> &str;
+
+    /// Embed one request's worth of texts, in order.
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String>;
+}
+
+/// Build the provider described by `config`.
+pub fn build_embedding_provider(
+    config: &EmbeddingProviderConfig,
+) -> Result<Box<dyn EmbeddingProvider>, String> {
+    match config.clone() {
+        EmbeddingProviderConfig::Ollama { base_url, model } => {
+            if model.trim().is_empty() {
+                return Err("Configura el modelo de embeddings de Ollama.".to_string());
+            }
+            let base_url = base_url
+                .filter(|u| !u.trim().is_empty())
+                .unwrap_or_else(|| OLLAMA_BASE_URL.to_string());
+            Ok(Box::new(OllamaProvider {
+                base_url: base_url.trim_end_matches('/').to_string(),
+                model,
+                client: reqwest::Client::new(),
+            }))
+        }
+        EmbeddingProviderConfig::OpenaiCompatible {
+            base_url,
+            api_key,
+            model,
+        } => {
+            if base_url.trim().is_empty() || model.trim().is_empty() {
+                return Err(
+                    "Configura la URL base y el modelo del servidor de embeddings.".to_string(),
+                );
+            }
+            Ok(Box::new(OpenAiCompatibleProvider {
+                base_url: base_url.trim_end_matches('/').to_string(),
+                api_key: api_key.filter(|k| !k.trim().is_empty()),
+                model,
+                client: reqwest::Client::new(),
+            }))
+        }
+    }
+}
+
+/// Embed any number of texts, batching requests to the provider.
+pub async fn embed_all(
+    provider: &dyn EmbeddingProvider,
+    texts: &[String],
+) -> Result<Vec<Vec<f32>>, String> {
+    let mut vectors = Vec::with_capacity(texts.len());
+    for batch in texts.chunks(EMBEDDING_BATCH_SIZE) {
+        let embedded = provider.embed_batch(batch).await?;
+        if embedded.len() != batch.len() {
+            return Err(format!(
+                "El servidor de embeddings devolvió {} vectores para {} textos.",
+                embedded.len(),
+                batch.len()
+            ));
+        }
+        vectors.extend(embedded);
+    }
+    Ok(vectors)
+}
+
+/// Cosine similarity of two vectors; 0 when either is empty or sizes differ.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let (mut dot, mut norm_a, mut norm_b) = (0.0f32, 0.0f32, 0.0f32);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a.sqrt() * norm_b.sqrt())
+    }
+}
+
+// ─── Chunking ──────────────────────────────────────────────────────────────────
+
+/// Split a transcript into passages of about `CHUNK_SECONDS` for embedding.
+/// Untimed transcripts are split by length and carry no timestamps.
+pub fn chunk_transcript(transcript: &Transcript) -> Vec<TranscriptSegment> {
+    if transcript.segments.is_empty() {
+        return chunk_text(&transcript.text);
+    }
+
+    let mut chunks: Vec<TranscriptSegment> = Vec::new();
+    let mut current: Option<TranscriptSegment> = None;
+    for segment in &transcript.segments {
+        let text = segment.text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        match current.as_mut() {
+            Some(chunk)
+                if segment.start - chunk.start < CHUNK_SECONDS
+                    && chunk.text.len() + text.len() < CHUNK_MAX_CHARS =>
+            {
+                chunk.text.push(' ');
+                chunk.text.push_str(text);
+                chunk.end = segment.end;
+            }
+            _ => {
+                chunks.extend(current.take());
+                current = Some(TranscriptSegment {
+                    start: segment.start,
+                    end: segment.end,
+                    text: text.to_string(),
+                });
+            }
+        }
+    }
+    chunks.extend(current);
+    chunks
+}
+
+/// Split plain text on word boundaries into pieces of at most `CHUNK_MAX_CHARS`.
+/// Start and end are set to -1 to mark them as untimed.
+fn chunk_text(text: &str) -> Vec<TranscriptSegment> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        if !current.is_empty() && current.len() + word.len() + 1 > CHUNK_MAX_CHARS {
+            chunks.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+        .into_iter()
+        .map(|text| TranscriptSegment {
+            start: -1.0,
+            end: -1.0,
+            text,
+        })
+        .collect()
+}
+
+// ─── Ollama ────────────────────────────────────────────────────────────────────
+
+pub struct OllamaProvider {
+    base_url: String,
+    model: String,
+    client: reqwest::Client,
+}
+
+#[async_trait]
+impl EmbeddingProvider for OllamaProvider {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        let response = self
+            .client
+            .post(format!("{}/api/embed", self.base_url))
+            .json(&serde_json::json!({ "model": self.model, "input": texts }))
+            .send()
+            .await
+            .map_err(|e| {
+                format!(
+                    "Error conectando con Ollama ({}): {}. ¿Está en ejecución?",
+                    self.base_url, e
+                )
+            })?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("Error de Ollama ({}): {}", status, body));
+        }
+
+        let json: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| format!("Error parseando respuesta de Ollama: {}", e))?;
+
+        parse_vectors(&json["embeddings"]).ok_or_else(|| "Respuesta inesperada de Ollama".into())
+    }
+}
+
+// ─── OpenAI-compatible ─────────────────────────────────────────────────────────
+
+pub struct OpenAiCompatibleProvider {
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+    client: reqwest::Client,
+}
+
+#[async_trait]
+impl EmbeddingProvider for OpenAiCompatibleProvider {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        let mut request = self
+            .client
+            .post(format!("{}/embeddings", self.base_url))
+            .json(&serde_json::json!({ "model": self.model, "input": texts }));
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("Error conectando con {}: {}", self.base_url, e))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            let msg = if status.as_u16() == 401 {
+                "API key del servidor de embeddings inválida. Verifica tu configuración en Ajustes."
+                    .to_string()
+            } else {
+                format!("Error del servidor de embeddings ({}): {}", status, body)
+            };
+            return Err(msg);
+        }
+
+        let json: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| format!("Error parseando respuesta del servidor de embeddings: {}", e))?;
+
+        // Results carry an `index`; sort by it rather than trusting the order.
+        let mut data: Vec<&serde_json::Value> =
+            json["data"].as_array().into_iter().flatten().collect();
+        data.sort_by_key(|d| d["index"].as_u64().unwrap_or(0));
+        let embeddings = serde_json::Value::Array(
+            data.into_iter().map(|d| d["embedding"].clone()).collect(),
+        );
+
+        parse_vectors(&embeddings)
+            .ok_or_else(|| "Respuesta inesperada del servidor de embeddings".into())
+    }
+}
+
+/// Parse a JSON array of number arrays.
+fn parse_vectors(value: &serde_json::Value) -> Option<Vec<Vec<f32>>> {
+    value
+        .as_array()?
+        .iter()
+        .map(|v| {
+            v.as_array()?
+                .iter()
+                .map(|x| x.as_f64().map(|x| x as f32))
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(start: f64, text: &str) -> TranscriptSegment {
+        TranscriptSegment {
+            start,
+            end: start + 5.0,
+            text: text.to_string(),
+        }
+    }
+
+    fn transcript(text: &str, segments: Vec<TranscriptSegment>) -> Transcript {
+        Transcript {
+            text: text.to_string(),
+            language: None,
+            segments,
+            cost_usd: 0.0,
+        }
+    }
+
+    #[test]
+    fn chunk_transcript_groups_segments_by_time() {
+        let chunks = chunk_transcript(&transcript(
+            "",
+            vec![
+                segment(0.0, "uno"),
+                segment(20.0, "  "),
+                segment(30.0, "dos"),
+                segment(50.0, "tres"),
+            ],
+        ));
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].text, "uno dos");
+        assert_eq!((chunks[0].start, chunks[0].end), (0.0, 35.0));
+        assert_eq!(chunks[1].text, "tres");
+        assert_eq!(chunks[1].start, 50.0);
+    }
+
+    #[test]
+    fn chunk_transcript_caps_chunk_length() {
+        let long = "a".repeat(CHUNK_MAX_CHARS - 10);
+        let chunks = chunk_transcript(&transcript(
+            "",
+            vec![segment(0.0, &long), segment(5.0, "palabras extra")],
+        ));
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[1].start, 5.0);
+    }
+
+    #[test]
+    fn chunk_transcript_splits_untimed_text_on_words() {
+        let text = vec!["palabra"; 300].join(" ");
+        let chunks = chunk_transcript(&transcript(&text, Vec::new()));
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks
+            .iter()
+            .all(|c| c.text.len() <= CHUNK_MAX_CHARS && c.start == -1.0));
+        assert_eq!(
+            chunks
+                .iter()
+                .map(|c| c.text.as_str())
+                .collect::<Vec<_>>()
+                .join(" "),
+            text
+        );
+    }
+
+    #[test]
+    fn cosine_similarity_handles_direction_and_degenerate_vectors() {
+        assert!((cosine_similarity(&[1.0, 2.0], &[2.0, 4.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+        assert!((cosine_similarity(&[1.0, 0.0], &[-1.0, 0.0]) + 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[], &[]), 0.0);
+        assert_eq!(cosine_similarity(&[1.0], &[1.0, 2.0]), 0.0);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 2.0]), 0.0);
+    }
+}
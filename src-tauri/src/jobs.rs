@@ -0,0 +1,695 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, State};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::commands::{
+    build_process_result, download_audio_as, emit_progress, export_outputs, find_captions,
+    get_app_data_dir, get_video_info, remove_temp_files, summarize_with_settings,
+    transcribe_downloaded, ProcessResult, ProcessSettings, SummaryResult, Transcript, VideoInfo,
+    CURRENT_JOB,
+};
+use crate::embeddings::EmbeddingProviderConfig;
+use crate::library::{index_result, record_result};
+use crate::summary::SummaryProviderConfig;
+use crate::templates::check_selected_template;
+use crate::transcription::TranscriptionProviderConfig;
+
+// ─── Data Types ────────────────────────────────────────────────────────────────
+
+/// Last pipeline stage a job has completed. Jobs resume from here after a restart.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStage {
+    Queued,
+    Downloaded,
+    Transcribed,
+    Summarized,
+    Exported,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Job {
+    pub id: String,
+    pub url: String,
+    /// Settings the job was queued with. API keys are never written to disk;
+    /// they come from the current settings whenever the job is (re)started.
+    pub settings: ProcessSettings,
+    pub stage: JobStage,
+    pub status: JobStatus,
+    pub error: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    pub video_info: Option<VideoInfo>,
+    /// Downloaded audio waiting for transcription.
+    pub audio_path: Option<String>,
+    pub transcript: Option<Transcript>,
+    pub summary: Option<SummaryResult>,
+    pub result: Option<ProcessResult>,
+}
+
+/// How many jobs may be inside each stage at the same time.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StageLimits {
+    pub download: usize,
+    pub transcribe: usize,
+    pub summarize: usize,
+    pub export: usize,
+}
+
+impl Default for StageLimits {
+    fn default() -> Self {
+        StageLimits {
+            download: 2,
+            transcribe: 1,
+            summarize: 2,
+            export: 2,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum StageKind {
+    Download,
+    Transcribe,
+    Summarize,
+    Export,
+}
+
+struct StageSemaphores {
+    download: Arc<Semaphore>,
+    transcribe: Arc<Semaphore>,
+    summarize: Arc<Semaphore>,
+    export: Arc<Semaphore>,
+}
+
+impl StageSemaphores {
+    fn new(limits: &StageLimits) -> Self {
+        StageSemaphores {
+            download: Arc::new(Semaphore::new(limits.download.max(1))),
+            transcribe: Arc::new(Semaphore::new(limits.transcribe.max(1))),
+            summarize: Arc::new(Semaphore::new(limits.summarize.max(1))),
+            export: Arc::new(Semaphore::new(limits.export.max(1))),
+        }
+    }
+
+    fn get(&self, kind: StageKind) -> Arc<Semaphore> {
+        match kind {
+            StageKind::Download => self.download.clone(),
+            StageKind::Transcribe => self.transcribe.clone(),
+            StageKind::Summarize => self.summarize.clone(),
+            StageKind::Export => self.export.clone(),
+        }
+    }
+}
+
+// ─── Queue ─────────────────────────────────────────────────────────────────────
+
+/// A job's background task and the number `spawn` gave it.
+type JobTask = (u64, JoinHandle<()>);
+
+/// Jobs persisted as one JSON file each under `<app_data>/jobs`.
+#[derive(Clone)]
+pub struct JobQueue {
+    dir: PathBuf,
+    jobs: Arc<Mutex<HashMap<String, Job>>>,
+    limits: Arc<Mutex<StageLimits>>,
+    semaphores: Arc<Mutex<StageSemaphores>>,
+    /// Background task of every job currently pending or running.
+    tasks: Arc<Mutex<HashMap<String, JobTask>>>,
+    next_task: Arc<AtomicU64>,
+    /// Latest settings sent by the frontend, the source of the API keys.
+    current_settings: Arc<Mutex<Option<ProcessSettings>>>,
+}
+
+const LIMITS_FILE: &str = "limits.json";
+
+fn now() -> String {
+    chrono::Local::now().to_rfc3339()
+}
+
+/// Write through a temporary file so a crash never leaves half a JSON file.
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|e| format!("Error serializando cola: {}", e))?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json).map_err(|e| format!("Error guardando cola: {}", e))?;
+    std::fs::rename(&tmp, path).map_err(|e| format!("Error guardando cola: {}", e))
+}
+
+/// `settings` without API keys, as stored in job files.
+fn without_secrets(settings: &ProcessSettings) -> ProcessSettings {
+    let mut settings = settings.clone();
+    settings.groq_api_key.clear();
+    settings.anthropic_api_key.clear();
+    settings.notion_api_key = None;
+    if let Some(TranscriptionProviderConfig::OpenaiCompatible { api_key, .. }) =
+        &mut settings.transcription_provider
+    {
+        *api_key = None;
+    }
+    if let Some(SummaryProviderConfig::OpenaiCompatible { api_key, .. }) =
+        &mut settings.summary_provider
+    {
+        *api_key = None;
+    }
+    if let Some(EmbeddingProviderConfig::OpenaiCompatible { api_key, .. }) =
+        &mut settings.embedding_provider
+    {
+        *api_key = None;
+    }
+    settings
+}
+
+fn same_config<T: Serialize>(a: &T, b: &T) -> bool {
+    serde_json::to_value(a).ok() == serde_json::to_value(b).ok()
+}
+
+/// `settings` with the API keys of `current`. A provider's key is only taken
+/// when the job still uses the same provider configuration.
+fn with_secrets(settings: &ProcessSettings, current: &ProcessSettings) -> ProcessSettings {
+    let (job, stripped) = (without_secrets(settings), without_secrets(current));
+
+    let mut settings = settings.clone();
+    settings.groq_api_key = current.groq_api_key.clone();
+    settings.anthropic_api_key = current.anthropic_api_key.clone();
+    settings.notion_api_key = current.notion_api_key.clone();
+    if same_config(
+        &job.transcription_provider,
+        &stripped.transcription_provider,
+    ) {
+        settings.transcription_provider = current.transcription_provider.clone();
+    }
+    if same_config(&job.summary_provider, &stripped.summary_provider) {
+        settings.summary_provider = current.summary_provider.clone();
+    }
+    if same_config(&job.embedding_provider, &stripped.embedding_provider) {
+        settings.embedding_provider = current.embedding_provider.clone();
+    }
+    settings
+}
+
+impl JobQueue {
+    /// Load persisted jobs and limits from the app data directory.
+    pub fn load(app: &AppHandle) -> Result<Self, String> {
+        let dir = get_app_data_dir(app)?.join("jobs");
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Error creando directorio de trabajos: {}", e))?;
+
+        let limits: StageLimits = std::fs::read_to_string(dir.join(LIMITS_FILE))
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+
+        let mut jobs = HashMap::new();
+        let entries = std::fs::read_dir(&dir)
+            .map_err(|e| format!("Error leyendo directorio de trabajos: {}", e))?;
+        for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+            if path.extension().and_then(|s| s.to_str()) != Some("json")
+                || path.file_name().and_then(|s| s.to_str()) == Some(LIMITS_FILE)
+            {
+                continue;
+            }
+            let job = std::fs::read_to_string(&path)
+                .ok()
+                .and_then(|raw| serde_json::from_str::<Job>(&raw).ok());
+            if let Some(mut job) = job {
+                // Files written by older versions still hold the API keys.
+                job.settings = without_secrets(&job.settings);
+                let _ = write_json(&path, &job);
+                jobs.insert(job.id.clone(), job);
+            }
+        }
+
+        Ok(JobQueue {
+            dir,
+            jobs: Arc::new(Mutex::new(jobs)),
+            semaphores: Arc::new(Mutex::new(StageSemaphores::new(&limits))),
+            limits: Arc::new(Mutex::new(limits)),
+            tasks: Arc::new(Mutex::new(HashMap::new())),
+            next_task: Arc::new(AtomicU64::new(0)),
+            current_settings: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    /// Remember the settings the frontend is using now.
+    fn set_current_settings(&self, settings: &ProcessSettings) {
+        *self.current_settings.lock().unwrap() = Some(settings.clone());
+    }
+
+    /// Restart every job that was pending or running when the app closed.
+    fn resume(&self, app: &AppHandle) {
+        let running = self
+            .tasks
+            .lock()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        let unfinished: Vec<String> = self
+            .jobs
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|j| matches!(j.status, JobStatus::Pending | JobStatus::Running))
+            .filter(|j| !running.contains(&j.id))
+            .map(|j| j.id.clone())
+            .collect();
+        for id in unfinished {
+            self.spawn(app, id);
+        }
+    }
+
+    fn spawn(&self, app: &AppHandle, id: String) {
+        let current = self.current_settings.lock().unwrap().clone();
+        if let Some(current) = current {
+            self.jobs
+                .lock()
+                .unwrap()
+                .entry(id.clone())
+                .and_modify(|job| job.settings = with_secrets(&job.settings, &current));
+        }
+
+        let queue = self.clone();
+        let app = app.clone();
+        let task_id = id.clone();
+        let number = self.next_task.fetch_add(1, Ordering::Relaxed);
+        // Held until the handle is stored, so a task that finishes at once
+        // cannot look for its entry before it exists.
+        let mut tasks = self.tasks.lock().unwrap();
+        let handle = tauri::async_runtime::spawn(CURRENT_JOB.scope(id.clone(), async move {
+            if let Err(e) = queue.drive(&app, &task_id).await {
+                emit_progress(&app, "error", &e, None);
+                queue.update(&app, &task_id, |job| {
+                    job.status = JobStatus::Failed;
+                    job.error = Some(e);
+                });
+            }
+            // A retry may already have replaced this task with a new one.
+            let mut tasks = queue.tasks.lock().unwrap();
+            if tasks.get(&task_id).is_some_and(|(n, _)| *n == number) {
+                tasks.remove(&task_id);
+            }
+        }));
+        tasks.insert(id, (number, handle));
+    }
+
+    /// Abort a job's task and wait for it to stop. Dropping the task kills its
+    /// yt-dlp / ffmpeg children (`kill_on_drop`) and aborts in-flight requests.
+    async fn cancel(&self, app: &AppHandle, id: &str) -> Result<Job, String> {
+        let handle = self.tasks.lock().unwrap().remove(id);
+        if let Some((_, handle)) = handle {
+            handle.abort();
+            let _ = handle.await;
+        }
+
+        let job = self.get(id).ok_or("Trabajo no encontrado.")?;
+        if !matches!(job.status, JobStatus::Pending | JobStatus::Running) {
+            return Err("El trabajo ya no está en curso.".to_string());
+        }
+
+        remove_temp_files(app, id);
+        let job = self
+            .update(app, id, |j| {
+                j.status = JobStatus::Cancelled;
+                j.error = None;
+                // Partial audio was just deleted; a retry downloads it again.
+                if j.stage == JobStage::Downloaded {
+                    j.audio_path = None;
+                    j.stage = JobStage::Queued;
+                }
+            })
+            .ok_or("Trabajo no encontrado.")?;
+        emit_progress(
+            app,
+            "cancelled",
+            &format!(
+                "Trabajo cancelado: {}",
+                job.video_info
+                    .as_ref()
+                    .map(|v| v.title.as_str())
+                    .unwrap_or(&job.url)
+            ),
+            None,
+        );
+        Ok(job)
+    }
+
+    fn get(&self, id: &str) -> Option<Job> {
+        self.jobs.lock().unwrap().get(id).cloned()
+    }
+
+    fn list(&self) -> Vec<Job> {
+        let mut jobs: Vec<Job> = self.jobs.lock().unwrap().values().cloned().collect();
+        jobs.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+        jobs
+    }
+
+    fn insert(&self, app: &AppHandle, job: Job) -> Result<(), String> {
+        self.persist(&job)?;
+        let _ = app.emit("job-updated", &job);
+        self.jobs.lock().unwrap().insert(job.id.clone(), job);
+        Ok(())
+    }
+
+    /// Apply `change` to a job, persist it and notify the frontend.
+    fn update(&self, app: &AppHandle, id: &str, change: impl FnOnce(&mut Job)) -> Option<Job> {
+        let job = {
+            let mut jobs = self.jobs.lock().unwrap();
+            let job = jobs.get_mut(id)?;
+            change(job);
+            job.updated_at = now();
+            job.clone()
+        };
+        if let Err(e) = self.persist(&job) {
+            emit_progress(app, "error", &e, None);
+        }
+        let _ = app.emit("job-updated", &job);
+        Some(job)
+    }
+
+    fn remove(&self, id: &str) -> Option<Job> {
+        let job = self.jobs.lock().unwrap().remove(id)?;
+        let _ = std::fs::remove_file(self.job_path(id));
+        Some(job)
+    }
+
+    fn job_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    /// Save a job without its API keys.
+    fn persist(&self, job: &Job) -> Result<(), String> {
+        let mut stored = job.clone();
+        stored.settings = without_secrets(&job.settings);
+        write_json(&self.job_path(&job.id), &stored)
+    }
+
+    async fn acquire(&self, kind: StageKind) -> Result<OwnedSemaphorePermit, String> {
+        let semaphore = self.semaphores.lock().unwrap().get(kind);
+        semaphore
+            .acquire_owned()
+            .await
+            .map_err(|_| "La cola de trabajos se cerró.".to_string())
+    }
+
+    fn set_limits(&self, limits: StageLimits) -> Result<(), String> {
+        write_json(&self.dir.join(LIMITS_FILE), &limits)?;
+        // Jobs already inside a stage keep their permit from the previous semaphore.
+        *self.semaphores.lock().unwrap() = StageSemaphores::new(&limits);
+        *self.limits.lock().unwrap() = limits;
+        Ok(())
+    }
+
+    /// Advance a job stage by stage until it is exported.
+    async fn drive(&self, app: &AppHandle, id: &str) -> Result<(), String> {
+        self.update(app, id, |job| {
+            job.status = JobStatus::Running;
+            job.error = None;
+        });
+
+        loop {
+            let job = self
+                .get(id)
+                .ok_or_else(|| format!("Trabajo {} no encontrado.", id))?;
+
+            match job.stage {
+                JobStage::Queued => {
+                    let _permit = self.acquire(StageKind::Download).await?;
+                    let video_info = match job.video_info {
+                        Some(info) => info,
+                        None => get_video_info(job.url.clone()).await?,
+                    };
+                    self.update(app, id, |j| j.video_info = Some(video_info));
+
+                    // Existing subtitles skip both download and transcription.
+                    if let Some(captions) = find_captions(app, &job.url, &job.settings, id).await? {
+                        self.update(app, id, |j| {
+                            j.transcript = Some(captions);
+                            j.stage = JobStage::Transcribed;
+                        });
+                        continue;
+                    }
+
+                    let audio_path =
+                        download_audio_as(app.clone(), job.url.clone(), id.to_string()).await?;
+                    self.update(app, id, |j| {
+                        j.audio_path = Some(audio_path);
+                        j.stage = JobStage::Downloaded;
+                    });
+                }
+                JobStage::Downloaded => {
+                    // Audio lost since the last run (e.g. temp folder cleaned) is downloaded again.
+                    let Some(audio_path) = job.audio_path.filter(|p| Path::new(p).is_file()) else {
+                        self.update(app, id, |j| {
+                            j.audio_path = None;
+                            j.stage = JobStage::Queued;
+                        });
+                        continue;
+                    };
+                    let _permit = self.acquire(StageKind::Transcribe).await?;
+                    // The audio is kept on failure so a retry does not download it again.
+                    let transcript = transcribe_downloaded(app, &audio_path, &job.settings).await?;
+                    let _ = tokio::fs::remove_file(&audio_path).await;
+                    self.update(app, id, |j| {
+                        j.audio_path = None;
+                        j.transcript = Some(transcript);
+                        j.stage = JobStage::Transcribed;
+                    });
+                }
+                JobStage::Transcribed => {
+                    let (Some(video_info), Some(transcript)) = (job.video_info, job.transcript)
+                    else {
+                        return Err("Faltan datos del video o la transcripción.".to_string());
+                    };
+                    let _permit = self.acquire(StageKind::Summarize).await?;
+                    let summary =
+                        summarize_with_settings(app, &video_info, &transcript.text, &job.settings)
+                            .await?;
+                    self.update(app, id, |j| {
+                        j.summary = Some(summary);
+                        j.stage = JobStage::Summarized;
+                    });
+                }
+                JobStage::Summarized => {
+                    let (Some(video_info), Some(transcript), Some(summary)) =
+                        (job.video_info, job.transcript, job.summary)
+                    else {
+                        return Err("Faltan datos del resumen.".to_string());
+                    };
+                    let _permit = self.acquire(StageKind::Export).await?;
+                    let (saved_path, notion_url) =
+                        export_outputs(app, &job.settings, &video_info, &summary, &transcript)
+                            .await?;
+                    let mut result = build_process_result(
+                        video_info,
+                        transcript,
+                        summary.clone(),
+                        saved_path,
+                        notion_url,
+                    );
+                    record_result(app, &mut result, &summary).await;
+                    index_result(app, &job.settings, &result).await;
+                    self.update(app, id, |j| {
+                        j.result = Some(result);
+                        j.stage = JobStage::Exported;
+                        j.status = JobStatus::Completed;
+                    });
+                    return Ok(());
+                }
+                JobStage::Exported => {
+                    self.update(app, id, |j| j.status = JobStatus::Completed);
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+// ─── Commands ─────────────────────────────────────────────────────────────────
+
+/// Queue one job per URL; they run in the background under the stage limits.
+#[tauri::command]
+pub async fn enqueue_jobs(
+    app: AppHandle,
+    queue: State<'_, JobQueue>,
+    urls: Vec<String>,
+    settings: ProcessSettings,
+) -> Result<Vec<Job>, String> {
+    check_selected_template(&settings)?;
+    queue.set_current_settings(&settings);
+    let mut created = Vec::with_capacity(urls.len());
+    for url in urls {
+        let timestamp = now();
+        let job = Job {
+            id: uuid::Uuid::new_v4().to_string(),
+            url,
+            settings: settings.clone(),
+            stage: JobStage::Queued,
+            status: JobStatus::Pending,
+            error: None,
+            created_at: timestamp.clone(),
+            updated_at: timestamp,
+            video_info: None,
+            audio_path: None,
+            transcript: None,
+            summary: None,
+            result: None,
+        };
+        queue.insert(&app, job.clone())?;
+        queue.spawn(&app, job.id.clone());
+        created.push(job);
+    }
+    Ok(created)
+}
+
+/// Restart the jobs left pending or running when the app closed, with the
+/// API keys of the current `settings`. Called once the frontend has loaded them.
+#[tauri::command]
+pub async fn resume_jobs(
+    app: AppHandle,
+    queue: State<'_, JobQueue>,
+    settings: ProcessSettings,
+) -> Result<Vec<Job>, String> {
+    queue.set_current_settings(&settings);
+    queue.resume(&app);
+    Ok(queue.list())
+}
+
+#[tauri::command]
+pub async fn list_jobs(queue: State<'_, JobQueue>) -> Result<Vec<Job>, String> {
+    Ok(queue.list())
+}
+
+/// Stop a pending or running job and delete its partial files.
+#[tauri::command]
+pub async fn cancel_job(
+    app: AppHandle,
+    queue: State<'_, JobQueue>,
+    job_id: String,
+) -> Result<Job, String> {
+    queue.cancel(&app, &job_id).await
+}
+
+/// Run a failed or cancelled job again from its last completed stage.
+#[tauri::command]
+pub async fn retry_job(
+    app: AppHandle,
+    queue: State<'_, JobQueue>,
+    job_id: String,
+) -> Result<Job, String> {
+    let job = queue.get(&job_id).ok_or("Trabajo no encontrado.")?;
+    if !matches!(job.status, JobStatus::Failed | JobStatus::Cancelled) {
+        return Err("Solo se pueden reintentar trabajos fallidos o cancelados.".to_string());
+    }
+    // Job files hold no API keys; without the frontend's settings the job
+    // would run with empty keys and fail again.
+    if queue.current_settings.lock().unwrap().is_none() {
+        return Err(
+            "Los ajustes aún no se han cargado; no hay API keys para reintentar el trabajo."
+                .to_string(),
+        );
+    }
+    let job = queue
+        .update(&app, &job_id, |j| {
+            j.status = JobStatus::Pending;
+            j.error = None;
+        })
+        .ok_or("Trabajo no encontrado.")?;
+    queue.spawn(&app, job_id);
+    Ok(job)
+}
+
+/// Forget a finished, failed or cancelled job, deleting any audio it left behind.
+#[tauri::command]
+pub async fn remove_job(queue: State<'_, JobQueue>, job_id: String) -> Result<(), String> {
+    let job = queue.get(&job_id).ok_or("Trabajo no encontrado.")?;
+    if matches!(job.status, JobStatus::Pending | JobStatus::Running) {
+        return Err("El trabajo sigue en curso.".to_string());
+    }
+    if let Some(removed) = queue.remove(&job_id) {
+        if let Some(audio_path) = removed.audio_path {
+            let _ = tokio::fs::remove_file(audio_path).await;
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_stage_limits(queue: State<'_, JobQueue>) -> Result<StageLimits, String> {
+    Ok(queue.limits.lock().unwrap().clone())
+}
+
+#[tauri::command]
+pub async fn set_stage_limits(
+    queue: State<'_, JobQueue>,
+    limits: StageLimits,
+) -> Result<StageLimits, String> {
+    queue.set_limits(limits.clone())?;
+    Ok(limits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_settings;
+
+    fn settings(key: &str, base_url: &str) -> ProcessSettings {
+        ProcessSettings {
+            groq_api_key: format!("gsk-{}", key),
+            anthropic_api_key: format!("sk-ant-{}", key),
+            notion_api_key: Some(format!("secret-{}", key)),
+            summary_provider: Some(SummaryProviderConfig::OpenaiCompatible {
+                base_url: base_url.to_string(),
+                api_key: Some(format!("sk-{}", key)),
+                input_price_per_mtok: None,
+                output_price_per_mtok: None,
+            }),
+            ..test_settings()
+        }
+    }
+
+    #[test]
+    fn without_secrets_clears_every_key() {
+        let stored = without_secrets(&settings("zq1", "https://a"));
+        let stored = serde_json::to_string(&stored).unwrap();
+        assert!(!stored.contains("zq1"));
+        assert!(stored.contains("https://a"));
+    }
+
+    #[test]
+    fn with_secrets_takes_current_keys_for_the_same_provider() {
+        let job = without_secrets(&settings("old", "https://a"));
+
+        let restored = with_secrets(&job, &settings("new", "https://a"));
+        assert_eq!(restored.groq_api_key, "gsk-new");
+        assert_eq!(restored.notion_api_key.as_deref(), Some("secret-new"));
+        assert!(matches!(
+            restored.summary_provider,
+            Some(SummaryProviderConfig::OpenaiCompatible { api_key: Some(ref k), .. })
+                if k == "sk-new"
+        ));
+
+        // A key for another server is not sent to the job's one.
+        let restored = with_secrets(&job, &settings("new", "https://b"));
+        assert!(matches!(
+            restored.summary_provider,
+            Some(SummaryProviderConfig::OpenaiCompatible { api_key: None, ref base_url, .. })
+                if base_url == "https://a"
+        ));
+    }
+}
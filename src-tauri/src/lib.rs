@@ -1,4 +1,16 @@
+use tauri::Manager;
+
+pub mod classify;
 pub mod commands;
+pub mod embeddings;
+pub mod jobs;
+pub mod library;
+pub mod notion;
+pub mod obsidian;
+pub mod summary;
+pub mod tags;
+pub mod templates;
+pub mod transcription;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -8,16 +20,51 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_opener::init())
+        .setup(|app| {
+            app.manage(library::Library::open(app.handle())?);
+            app.manage(jobs::JobQueue::load(app.handle())?);
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             commands::get_video_info,
             commands::download_audio,
+            commands::fetch_captions,
             commands::transcribe_audio,
+            commands::transcribe_audio_timestamped,
             commands::generate_summary,
             commands::send_to_notion,
             commands::save_markdown,
+            commands::save_subtitles,
+            obsidian::save_obsidian_note,
+            templates::get_default_markdown_template,
+            templates::preview_markdown_template,
+            classify::classify_video,
             commands::open_folder,
             commands::check_dependencies,
             commands::get_default_output_dir,
+            commands::process_video,
+            commands::expand_source,
+            commands::process_batch,
+            jobs::enqueue_jobs,
+            jobs::resume_jobs,
+            jobs::list_jobs,
+            jobs::cancel_job,
+            jobs::retry_job,
+            jobs::remove_job,
+            jobs::get_stage_limits,
+            jobs::set_stage_limits,
+            library::list_library,
+            library::search_library,
+            library::semantic_search_library,
+            library::index_library,
+            library::ask_library,
+            library::chat_with_video,
+            library::get_video_chat,
+            library::clear_video_chat,
+            library::get_library_entry,
+            library::delete_library_entry,
+            library::reexport_library_entry,
+            notion::inspect_notion_database,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,1426 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager, State};
+
+use crate::commands::{
+    emit_progress, export_outputs, fold_text, format_timestamp, get_app_data_dir, timestamp_url,
+    video_key, ProcessResult, ProcessSettings, SummaryResult, Transcript, TranscriptSegment,
+    VideoInfo,
+};
+use crate::embeddings::{
+    build_embedding_provider, chunk_transcript, cosine_similarity, embed_all, EmbeddingProvider,
+};
+use crate::summary::{build_summary_provider, ChatMessage, StructuredSummary};
+
+// ─── Data Types ────────────────────────────────────────────────────────────────
+
+/// A place a processed video was exported to.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExportRecord {
+    pub target: String, // "markdown" | "notion"
+    pub location: String, // file path or page URL
+    pub exported_at: String,
+}
+
+/// Library row as shown in listings (no transcript).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LibraryItem {
+    pub id: String,
+    pub video_info: VideoInfo,
+    pub processed_at: String,
+    pub language: Option<String>,
+    pub tokens_used: u32,
+    /// Cost of the summary.
+    pub cost_usd: f64,
+    pub transcription_cost_usd: f64,
+    pub exports: Vec<ExportRecord>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LibraryEntry {
+    #[serde(flatten)]
+    pub item: LibraryItem,
+    pub summary: String,
+    pub summary_provider: String,
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    pub transcript: String,
+    pub segments: Vec<TranscriptSegment>,
+    pub structured: Option<StructuredSummary>,
+}
+
+/// A full-text match. Highlighted fields are HTML: the stored text escaped,
+/// with matched terms wrapped in `<mark>…</mark>`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchHit {
+    pub id: String,
+    pub video_info: VideoInfo,
+    pub title_highlight: String,
+    pub snippet: String,
+    /// Start of the transcript segment that best matches the query, if timed.
+    pub timestamp: Option<f64>,
+    pub timestamp_url: Option<String>,
+}
+
+/// A transcript passage retrieved by meaning rather than keywords.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LibraryPassage {
+    pub video_id: String,
+    pub video_info: VideoInfo,
+    pub text: String,
+    /// Start of the passage in seconds; `None` for untimed transcripts.
+    pub timestamp: Option<f64>,
+    pub timestamp_url: Option<String>,
+    pub score: f32,
+}
+
+/// Answer to a question about the library. `[n]` markers in `answer` refer to
+/// `sources[n - 1]`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LibraryAnswer {
+    pub answer: String,
+    pub sources: Vec<LibraryPassage>,
+    pub provider: String,
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    pub cost_usd: f64,
+}
+
+/// Outcome of `index_library`: videos embedded and those skipped after an error.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IndexReport {
+    pub indexed: usize,
+    pub failed: Vec<IndexFailure>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IndexFailure {
+    pub video_id: String,
+    pub title: String,
+    pub error: String,
+}
+
+/// Stored conversation about one library video, with its accumulated usage.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VideoChat {
+    pub video_id: String,
+    pub messages: Vec<ChatMessage>,
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    pub cost_usd: f64,
+}
+
+/// Reply to one chat turn, with usage reported like `SummaryResult`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChatResult {
+    pub reply: String,
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    pub total_tokens: u32,
+    /// Part of `input_tokens` served from the prompt cache.
+    pub cached_tokens: u32,
+    pub cost_usd: f64,
+    pub provider: String,
+}
+
+/// Passages handed to the model by `ask_library` unless the caller asks otherwise.
+const ASK_DEFAULT_PASSAGES: usize = 8;
+const ASK_MAX_TOKENS: u32 = 2048;
+
+const ASK_SYSTEM_PROMPT: &str = "Eres un asistente que responde preguntas usando únicamente \
+fragmentos de transcripciones de videos de la biblioteca del usuario. Cada fragmento viene \
+numerado como [n] junto con el título del video y el minuto. Responde en el idioma de la \
+pregunta, de forma concisa, y cita cada afirmación con el número del fragmento entre corchetes, \
+por ejemplo [2] o [1][3]. Si los fragmentos no contienen la respuesta, dilo claramente en lugar \
+de inventarla.";
+
+const CHAT_MAX_TOKENS: u32 = 2048;
+/// Transcript budget of the chat context, in estimated tokens. Longer
+/// transcripts are cut at the same point on every turn, so the cached prefix
+/// stays identical; the summary still covers the whole video.
+const CHAT_CONTEXT_MAX_TOKENS: usize = 30_000;
+
+const CHAT_SYSTEM_PROMPT: &str = "Eres un asistente que conversa con el usuario sobre un \
+video. A continuación tienes sus datos, el resumen generado y la transcripción completa con \
+marcas de tiempo. Responde en el idioma del usuario basándote en la transcripción; cuando sea \
+útil, indica el minuto [MM:SS] donde se menciona algo. Si el video no trata lo que se pregunta, \
+dilo.";
+
+// ─── Database ──────────────────────────────────────────────────────────────────
+
+/// Schema changes, applied in order and tracked with `PRAGMA user_version`.
+const MIGRATIONS: &[&str] = &["
+    CREATE TABLE videos (
+        seq                    INTEGER PRIMARY KEY,
+        id                     TEXT NOT NULL UNIQUE,
+        url                    TEXT NOT NULL UNIQUE,
+        video_key              TEXT NOT NULL,
+        title                  TEXT NOT NULL,
+        channel                TEXT NOT NULL,
+        duration               INTEGER NOT NULL,
+        thumbnail              TEXT,
+        description            TEXT,
+        upload_date            TEXT,
+        language               TEXT,
+        transcript             TEXT NOT NULL,
+        segments               TEXT NOT NULL,
+        summary                TEXT NOT NULL,
+        summary_provider       TEXT NOT NULL,
+        input_tokens           INTEGER NOT NULL,
+        output_tokens          INTEGER NOT NULL,
+        cost_usd               REAL NOT NULL,
+        transcription_cost_usd REAL NOT NULL DEFAULT 0,
+        processed_at           TEXT NOT NULL
+    );
+    CREATE UNIQUE INDEX videos_video_key ON videos(video_key);
+    CREATE TABLE exports (
+        id          INTEGER PRIMARY KEY AUTOINCREMENT,
+        video_id    TEXT NOT NULL REFERENCES videos(id) ON DELETE CASCADE,
+        target      TEXT NOT NULL,
+        location    TEXT NOT NULL,
+        exported_at TEXT NOT NULL
+    );
+    CREATE INDEX exports_video_id ON exports(video_id);
+", "
+    CREATE VIRTUAL TABLE videos_fts USING fts5(
+        title, channel, summary, transcript,
+        content = 'videos', content_rowid = 'seq',
+        tokenize = 'unicode61 remove_diacritics 2'
+    );
+    CREATE TRIGGER videos_fts_insert AFTER INSERT ON videos BEGIN
+        INSERT INTO videos_fts (rowid, title, channel, summary, transcript)
+        VALUES (new.seq, new.title, new.channel, new.summary, new.transcript);
+    END;
+    CREATE TRIGGER videos_fts_delete AFTER DELETE ON videos BEGIN
+        INSERT INTO videos_fts (videos_fts, rowid, title, channel, summary, transcript)
+        VALUES ('delete', old.seq, old.title, old.channel, old.summary, old.transcript);
+    END;
+    CREATE TRIGGER videos_fts_update AFTER UPDATE ON videos BEGIN
+        INSERT INTO videos_fts (videos_fts, rowid, title, channel, summary, transcript)
+        VALUES ('delete', old.seq, old.title, old.channel, old.summary, old.transcript);
+        INSERT INTO videos_fts (rowid, title, channel, summary, transcript)
+        VALUES (new.seq, new.title, new.channel, new.summary, new.transcript);
+    END;
+    INSERT INTO videos_fts (videos_fts) VALUES ('rebuild');
+", "
+    CREATE TABLE chunks (
+        id         INTEGER PRIMARY KEY AUTOINCREMENT,
+        video_id   TEXT NOT NULL REFERENCES videos(id) ON DELETE CASCADE,
+        start_time REAL,
+        end_time   REAL,
+        text       TEXT NOT NULL,
+        model      TEXT NOT NULL,
+        embedding  BLOB NOT NULL
+    );
+    CREATE INDEX chunks_video_id ON chunks(video_id);
+    CREATE TABLE indexed_videos (
+        video_id   TEXT NOT NULL REFERENCES videos(id) ON DELETE CASCADE,
+        model      TEXT NOT NULL,
+        indexed_at TEXT NOT NULL,
+        PRIMARY KEY (video_id, model)
+    );
+", "
+    CREATE TABLE chat_messages (
+        id            INTEGER PRIMARY KEY AUTOINCREMENT,
+        video_id      TEXT NOT NULL REFERENCES videos(id) ON DELETE CASCADE,
+        role          TEXT NOT NULL,
+        content       TEXT NOT NULL,
+        input_tokens  INTEGER NOT NULL DEFAULT 0,
+        output_tokens INTEGER NOT NULL DEFAULT 0,
+        cost_usd      REAL NOT NULL DEFAULT 0,
+        created_at    TEXT NOT NULL
+    );
+    CREATE INDEX chat_messages_video_id ON chat_messages(video_id);
+", "
+    ALTER TABLE videos ADD COLUMN structured TEXT;
+"];
+
+/// Every processed video, stored in `<app_data>/library.sqlite3`.
+#[derive(Clone)]
+pub struct Library {
+    conn: Arc<Mutex<Connection>>,
+}
+
+/// Markers around matched terms; control characters never appear in stored text.
+const MATCH_START: &str = "\u{1}";
+const MATCH_END: &str = "\u{2}";
+
+/// Escape FTS output as HTML and turn the match markers into `<mark>` tags.
+fn highlight_html(text: &str) -> String {
+    let mut html = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            '\u{1}' => html.push_str("<mark>"),
+            '\u{2}' => html.push_str("</mark>"),
+            c => html.push(c),
+        }
+    }
+    html
+}
+
+fn db_error(e: rusqlite::Error) -> String {
+    format!("Error de la biblioteca: {}", e)
+}
+
+impl Library {
+    pub fn open(app: &AppHandle) -> Result<Self, String> {
+        let dir = get_app_data_dir(app)?;
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Error creando directorio de datos: {}", e))?;
+        Self::open_path(&dir.join("library.sqlite3"))
+    }
+
+    fn open_path(path: &std::path::Path) -> Result<Self, String> {
+        let conn = Connection::open(path).map_err(db_error)?;
+        conn.execute_batch("PRAGMA foreign_keys = ON; PRAGMA journal_mode = WAL;")
+            .map_err(db_error)?;
+
+        let version: usize = conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(db_error)?;
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            conn.execute_batch(&format!(
+                "BEGIN; {} PRAGMA user_version = {}; COMMIT;",
+                migration,
+                index + 1
+            ))
+            .map_err(db_error)?;
+        }
+
+        Ok(Library {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    /// Run `f` on the blocking thread pool so SQLite never stalls the async runtime.
+    async fn blocking<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&Library) -> Result<T, String> + Send + 'static,
+    ) -> Result<T, String> {
+        let library = self.clone();
+        tokio::task::spawn_blocking(move || f(&library))
+            .await
+            .map_err(|e| format!("Error de la biblioteca: {}", e))?
+    }
+
+    /// Insert or refresh the row for the video of `result.video_info.url` (any
+    /// variant of its URL) and record its exports. Returns the library id,
+    /// stable across re-processing.
+    fn upsert(&self, result: &ProcessResult, summary: &SummaryResult) -> Result<String, String> {
+        let conn = self.conn.lock().unwrap();
+        let info = &result.video_info;
+        let segments = serde_json::to_string(&result.segments)
+            .map_err(|e| format!("Error serializando segmentos: {}", e))?;
+        let structured = summary
+            .structured
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| format!("Error serializando resumen estructurado: {}", e))?;
+        let now = chrono::Local::now().to_rfc3339();
+
+        let id: String = conn
+            .query_row(
+                "INSERT INTO videos (id, url, title, channel, duration, thumbnail, description,
+                     upload_date, language, transcript, segments, summary, summary_provider,
+                     input_tokens, output_tokens, cost_usd, processed_at, structured, video_key,
+                     transcription_cost_usd)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17,
+                     ?18, ?19, ?20)
+                 ON CONFLICT(video_key) DO UPDATE SET
+                     url = excluded.url, title = excluded.title, channel = excluded.channel,
+                     duration = excluded.duration, thumbnail = excluded.thumbnail,
+                     description = excluded.description, upload_date = excluded.upload_date,
+                     language = excluded.language, transcript = excluded.transcript,
+                     segments = excluded.segments, summary = excluded.summary,
+                     summary_provider = excluded.summary_provider,
+                     input_tokens = excluded.input_tokens, output_tokens = excluded.output_tokens,
+                     cost_usd = excluded.cost_usd, processed_at = excluded.processed_at,
+                     structured = excluded.structured,
+                     transcription_cost_usd = excluded.transcription_cost_usd
+                 RETURNING id",
+                params![
+                    uuid::Uuid::new_v4().to_string(),
+                    info.url,
+                    info.title,
+                    info.channel,
+                    info.duration as i64,
+                    info.thumbnail,
+                    info.description,
+                    info.upload_date,
+                    result.language,
+                    result.transcript,
+                    segments,
+                    result.summary,
+                    summary.provider,
+                    summary.input_tokens,
+                    summary.output_tokens,
+                    summary.cost_usd,
+                    now,
+                    structured,
+                    video_key(&info.url),
+                    result.transcription_cost_usd,
+                ],
+                |row| row.get(0),
+            )
+            .map_err(db_error)?;
+
+        // The transcript may have changed; stale vectors are rebuilt on the next indexing.
+        conn.execute("DELETE FROM chunks WHERE video_id = ?1", params![id])
+            .map_err(db_error)?;
+        conn.execute(
+            "DELETE FROM indexed_videos WHERE video_id = ?1",
+            params![id],
+        )
+        .map_err(db_error)?;
+        insert_exports(
+            &conn,
+            &id,
+            result.saved_path.as_deref(),
+            result.notion_url.as_deref(),
+        )?;
+        Ok(id)
+    }
+
+    fn list(&self, limit: usize, offset: usize) -> Result<Vec<LibraryItem>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, url, title, channel, duration, thumbnail, description, upload_date,
+                     language, input_tokens + output_tokens, cost_usd, processed_at,
+                     transcription_cost_usd
+                 FROM videos ORDER BY processed_at DESC LIMIT ?1 OFFSET ?2",
+            )
+            .map_err(db_error)?;
+        let items = stmt
+            .query_map(params![limit as i64, offset as i64], item_from_row)
+            .map_err(db_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(db_error)?;
+
+        items
+            .into_iter()
+            .map(|mut item| {
+                item.exports = exports_of(&conn, &item.id)?;
+                Ok(item)
+            })
+            .collect()
+    }
+
+    fn get(&self, id: &str) -> Result<Option<LibraryEntry>, String> {
+        let conn = self.conn.lock().unwrap();
+        let entry = conn
+            .query_row(
+                "SELECT id, url, title, channel, duration, thumbnail, description, upload_date,
+                     language, input_tokens + output_tokens, cost_usd, processed_at,
+                     transcription_cost_usd, summary, summary_provider, input_tokens,
+                     output_tokens, transcript, segments, structured
+                 FROM videos WHERE id = ?1",
+                params![id],
+                |row| {
+                    let segments: String = row.get(18)?;
+                    let structured: Option<String> = row.get(19)?;
+                    Ok(LibraryEntry {
+                        item: item_from_row(row)?,
+                        summary: row.get(13)?,
+                        summary_provider: row.get(14)?,
+                        input_tokens: row.get(15)?,
+                        output_tokens: row.get(16)?,
+                        transcript: row.get(17)?,
+                        segments: serde_json::from_str(&segments).unwrap_or_default(),
+                        structured: structured.and_then(|s| serde_json::from_str(&s).ok()),
+                    })
+                },
+            )
+            .optional()
+            .map_err(db_error)?;
+
+        match entry {
+            Some(mut entry) => {
+                entry.item.exports = exports_of(&conn, id)?;
+                Ok(Some(entry))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn delete(&self, id: &str) -> Result<bool, String> {
+        let conn = self.conn.lock().unwrap();
+        let deleted = conn
+            .execute("DELETE FROM videos WHERE id = ?1", params![id])
+            .map_err(db_error)?;
+        Ok(deleted > 0)
+    }
+
+    /// Rank videos against `query` with FTS5 (title > summary > channel > transcript).
+    fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>, String> {
+        let terms = search_terms(query);
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+        // Quote every term so user input never parses as FTS syntax; the last
+        // one is a prefix so results appear while typing.
+        let fts_query = terms
+            .iter()
+            .map(|t| format!("\"{}\"", t))
+            .collect::<Vec<_>>()
+            .join(" ")
+            + "*";
+
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare(
+                "SELECT v.id, v.url, v.title, v.channel, v.duration, v.thumbnail, v.description,
+                     v.upload_date, v.segments,
+                     highlight(videos_fts, 0, ?3, ?4),
+                     snippet(videos_fts, -1, ?3, ?4, '…', 24)
+                 FROM videos_fts JOIN videos v ON v.seq = videos_fts.rowid
+                 WHERE videos_fts MATCH ?1
+                 ORDER BY bm25(videos_fts, 10.0, 2.0, 5.0, 1.0)
+                 LIMIT ?2",
+            )
+            .map_err(db_error)?;
+
+        let hits = stmt
+            .query_map(
+                params![fts_query, limit as i64, MATCH_START, MATCH_END],
+                |row| {
+                    let url: String = row.get(1)?;
+                    let segments: String = row.get(8)?;
+                    let segments: Vec<TranscriptSegment> =
+                        serde_json::from_str(&segments).unwrap_or_default();
+                    let timestamp = best_matching_segment(&segments, &terms);
+                    Ok(SearchHit {
+                        id: row.get(0)?,
+                        timestamp_url: timestamp.map(|t| timestamp_url(&url, t)),
+                        timestamp,
+                        video_info: VideoInfo {
+                            url,
+                            title: row.get(2)?,
+                            channel: row.get(3)?,
+                            duration: row.get::<_, i64>(4)? as u64,
+                            thumbnail: row.get(5)?,
+                            description: row.get(6)?,
+                            upload_date: row.get(7)?,
+                        },
+                        title_highlight: highlight_html(&row.get::<_, String>(9)?),
+                        snippet: highlight_html(&row.get::<_, String>(10)?),
+                    })
+                },
+            )
+            .map_err(db_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(db_error);
+        hits
+    }
+
+    /// Ids of videos not yet indexed with `model`. Videos whose transcript
+    /// produced no passages count as indexed once tried.
+    fn unindexed(&self, model: &str) -> Result<Vec<String>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare(
+                "SELECT id FROM videos v WHERE NOT EXISTS
+                     (SELECT 1 FROM indexed_videos i WHERE i.video_id = v.id AND i.model = ?1)
+                 ORDER BY processed_at DESC",
+            )
+            .map_err(db_error)?;
+        let ids = stmt
+            .query_map(params![model], |row| row.get(0))
+            .map_err(db_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(db_error);
+        ids
+    }
+
+    /// Replace every stored passage of `video_id` with `chunks` and their
+    /// vectors, and mark the video as indexed with `model`.
+    fn replace_chunks(
+        &self,
+        video_id: &str,
+        model: &str,
+        chunks: &[TranscriptSegment],
+        vectors: &[Vec<f32>],
+    ) -> Result<(), String> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(db_error)?;
+        tx.execute("DELETE FROM chunks WHERE video_id = ?1", params![video_id])
+            .map_err(db_error)?;
+        for (chunk, vector) in chunks.iter().zip(vectors) {
+            let timed = chunk.start >= 0.0;
+            tx.execute(
+                "INSERT INTO chunks (video_id, start_time, end_time, text, model, embedding)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    video_id,
+                    timed.then_some(chunk.start),
+                    timed.then_some(chunk.end),
+                    chunk.text,
+                    model,
+                    vector_to_blob(vector),
+                ],
+            )
+            .map_err(db_error)?;
+        }
+        tx.execute(
+            "DELETE FROM indexed_videos WHERE video_id = ?1",
+            params![video_id],
+        )
+        .map_err(db_error)?;
+        tx.execute(
+            "INSERT INTO indexed_videos (video_id, model, indexed_at) VALUES (?1, ?2, ?3)",
+            params![video_id, model, chrono::Local::now().to_rfc3339()],
+        )
+        .map_err(db_error)?;
+        tx.commit().map_err(db_error)
+    }
+
+    /// The `limit` passages embedded with `model` closest to `query`.
+    fn nearest_chunks(
+        &self,
+        query: &[f32],
+        model: &str,
+        limit: usize,
+    ) -> Result<Vec<LibraryPassage>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare(
+                "SELECT v.id, v.url, v.title, v.channel, v.duration, v.thumbnail, v.description,
+                     v.upload_date, c.text, c.start_time, c.embedding
+                 FROM chunks c JOIN videos v ON v.id = c.video_id
+                 WHERE c.model = ?1",
+            )
+            .map_err(db_error)?;
+
+        let mut passages = stmt
+            .query_map(params![model], |row| {
+                let url: String = row.get(1)?;
+                let timestamp: Option<f64> = row.get(9)?;
+                let embedding: Vec<u8> = row.get(10)?;
+                Ok(LibraryPassage {
+                    video_id: row.get(0)?,
+                    timestamp_url: timestamp.map(|t| timestamp_url(&url, t)),
+                    timestamp,
+                    video_info: VideoInfo {
+                        url,
+                        title: row.get(2)?,
+                        channel: row.get(3)?,
+                        duration: row.get::<_, i64>(4)? as u64,
+                        thumbnail: row.get(5)?,
+                        description: row.get(6)?,
+                        upload_date: row.get(7)?,
+                    },
+                    text: row.get(8)?,
+                    score: cosine_similarity(query, &blob_to_vector(&embedding)),
+                })
+            })
+            .map_err(db_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(db_error)?;
+
+        passages.sort_by(|a, b| b.score.total_cmp(&a.score));
+        passages.truncate(limit);
+        Ok(passages)
+    }
+
+    fn chat(&self, video_id: &str) -> Result<VideoChat, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare(
+                "SELECT role, content, input_tokens, output_tokens, cost_usd
+                 FROM chat_messages WHERE video_id = ?1 ORDER BY id",
+            )
+            .map_err(db_error)?;
+        let rows = stmt
+            .query_map(params![video_id], |row| {
+                Ok((
+                    ChatMessage {
+                        role: row.get(0)?,
+                        content: row.get(1)?,
+                    },
+                    row.get::<_, u32>(2)?,
+                    row.get::<_, u32>(3)?,
+                    row.get::<_, f64>(4)?,
+                ))
+            })
+            .map_err(db_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(db_error)?;
+
+        let mut chat = VideoChat {
+            video_id: video_id.to_string(),
+            messages: Vec::with_capacity(rows.len()),
+            input_tokens: 0,
+            output_tokens: 0,
+            cost_usd: 0.0,
+        };
+        for (message, input_tokens, output_tokens, cost_usd) in rows {
+            chat.messages.push(message);
+            chat.input_tokens += input_tokens;
+            chat.output_tokens += output_tokens;
+            chat.cost_usd += cost_usd;
+        }
+        Ok(chat)
+    }
+
+    /// Append the user's `messages` and the assistant's `reply` (which carries
+    /// the usage of the turn) to the conversation about `video_id`.
+    fn append_chat(
+        &self,
+        video_id: &str,
+        messages: &[ChatMessage],
+        reply: &ChatResult,
+    ) -> Result<(), String> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(db_error)?;
+        let now = chrono::Local::now().to_rfc3339();
+        for message in messages {
+            tx.execute(
+                "INSERT INTO chat_messages (video_id, role, content, created_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![video_id, message.role, message.content, now],
+            )
+            .map_err(db_error)?;
+        }
+        tx.execute(
+            "INSERT INTO chat_messages
+                 (video_id, role, content, input_tokens, output_tokens, cost_usd, created_at)
+             VALUES (?1, 'assistant', ?2, ?3, ?4, ?5, ?6)",
+            params![
+                video_id,
+                reply.reply,
+                reply.input_tokens,
+                reply.output_tokens,
+                reply.cost_usd,
+                now
+            ],
+        )
+        .map_err(db_error)?;
+        tx.commit().map_err(db_error)
+    }
+
+    fn clear_chat(&self, video_id: &str) -> Result<(), String> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM chat_messages WHERE video_id = ?1",
+            params![video_id],
+        )
+        .map_err(db_error)?;
+        Ok(())
+    }
+
+    fn add_exports(
+        &self,
+        id: &str,
+        saved_path: Option<&str>,
+        notion_url: Option<&str>,
+    ) -> Result<(), String> {
+        let conn = self.conn.lock().unwrap();
+        insert_exports(&conn, id, saved_path, notion_url)
+    }
+}
+
+/// Split a search query into folded words, dropping FTS operators and punctuation.
+fn search_terms(query: &str) -> Vec<String> {
+    fold_text(query)
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_string())
+        .collect()
+}
+
+/// Start of the segment matching the most query terms (earliest on ties).
+fn best_matching_segment(segments: &[TranscriptSegment], terms: &[String]) -> Option<f64> {
+    let mut best: Option<(usize, f64)> = None;
+    for segment in segments {
+        let text = fold_text(&segment.text);
+        let matched = terms.iter().filter(|t| text.contains(t.as_str())).count();
+        if matched > 0 && best.is_none_or(|(count, _)| matched > count) {
+            best = Some((matched, segment.start));
+        }
+    }
+    best.map(|(_, start)| start)
+}
+
+fn vector_to_blob(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+fn blob_to_vector(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+/// Map the first thirteen columns shared by the list and detail queries.
+fn item_from_row(row: &rusqlite::Row) -> rusqlite::Result<LibraryItem> {
+    Ok(LibraryItem {
+        id: row.get(0)?,
+        video_info: VideoInfo {
+            url: row.get(1)?,
+            title: row.get(2)?,
+            channel: row.get(3)?,
+            duration: row.get::<_, i64>(4)? as u64,
+            thumbnail: row.get(5)?,
+            description: row.get(6)?,
+            upload_date: row.get(7)?,
+        },
+        language: row.get(8)?,
+        tokens_used: row.get(9)?,
+        cost_usd: row.get(10)?,
+        processed_at: row.get(11)?,
+        transcription_cost_usd: row.get(12)?,
+        exports: Vec::new(),
+    })
+}
+
+fn exports_of(conn: &Connection, video_id: &str) -> Result<Vec<ExportRecord>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT target, location, exported_at FROM exports
+             WHERE video_id = ?1 ORDER BY id",
+        )
+        .map_err(db_error)?;
+    let exports = stmt
+        .query_map(params![video_id], |row| {
+            Ok(ExportRecord {
+                target: row.get(0)?,
+                location: row.get(1)?,
+                exported_at: row.get(2)?,
+            })
+        })
+        .map_err(db_error)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(db_error);
+    exports
+}
+
+fn insert_exports(
+    conn: &Connection,
+    video_id: &str,
+    saved_path: Option<&str>,
+    notion_url: Option<&str>,
+) -> Result<(), String> {
+    let now = chrono::Local::now().to_rfc3339();
+    for (target, location) in [("markdown", saved_path), ("notion", notion_url)] {
+        let Some(location) = location.filter(|l| !l.is_empty()) else {
+            continue;
+        };
+        conn.execute(
+            "INSERT INTO exports (video_id, target, location, exported_at) VALUES (?1, ?2, ?3, ?4)",
+            params![video_id, target, location, now],
+        )
+        .map_err(db_error)?;
+    }
+    Ok(())
+}
+
+/// Store a finished pipeline run and set `result.library_id`. A failure is
+/// reported as progress but never fails the run that produced the result.
+pub(crate) async fn record_result(
+    app: &AppHandle,
+    result: &mut ProcessResult,
+    summary: &SummaryResult,
+) {
+    let Some(library) = app.try_state::<Library>() else {
+        return;
+    };
+    let (stored, summary) = (result.clone(), summary.clone());
+    match library
+        .blocking(move |l| l.upsert(&stored, &summary))
+        .await
+    {
+        Ok(id) => result.library_id = Some(id),
+        Err(e) => emit_progress(
+            app,
+            "library",
+            &format!("⚠ No se pudo guardar en la biblioteca: {}", e),
+            None,
+        ),
+    }
+}
+
+/// Embed the transcript of library video `id` with `provider`.
+async fn index_video(
+    library: &Library,
+    provider: &dyn EmbeddingProvider,
+    id: &str,
+) -> Result<(), String> {
+    let entry_id = id.to_string();
+    let entry = library
+        .blocking(move |l| l.get(&entry_id))
+        .await?
+        .ok_or("El video no está en la biblioteca.")?;
+    let chunks = chunk_transcript(&Transcript {
+        text: entry.transcript,
+        language: entry.item.language,
+        segments: entry.segments,
+        cost_usd: 0.0,
+    });
+    let texts: Vec<String> = chunks.iter().map(|c| c.text.clone()).collect();
+    let vectors = embed_all(provider, &texts).await?;
+    let (id, model) = (id.to_string(), provider.model().to_string());
+    library
+        .blocking(move |l| l.replace_chunks(&id, &model, &chunks, &vectors))
+        .await
+}
+
+/// Embed a freshly stored result when semantic search is configured. Like
+/// `record_result`, a failure is only reported as progress.
+pub(crate) async fn index_result(app: &AppHandle, settings: &ProcessSettings, result: &ProcessResult) {
+    let (Some(config), Some(id)) = (&settings.embedding_provider, &result.library_id) else {
+        return;
+    };
+    let Some(library) = app.try_state::<Library>() else {
+        return;
+    };
+    emit_progress(app, "library", "Indexando para búsqueda semántica...", None);
+    let indexed = match build_embedding_provider(config) {
+        Ok(provider) => index_video(&library, provider.as_ref(), id).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = indexed {
+        emit_progress(
+            app,
+            "library",
+            &format!("⚠ No se pudo indexar para búsqueda semántica: {}", e),
+            None,
+        );
+    }
+}
+
+/// Rough token count (about four characters per token) used to budget context.
+fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// The transcript as passages of about a minute, ready for the chat context.
+fn transcript_passages(entry: &LibraryEntry) -> Vec<TranscriptSegment> {
+    chunk_transcript(&Transcript {
+        text: entry.transcript.clone(),
+        language: entry.item.language.clone(),
+        segments: entry.segments.clone(),
+        cost_usd: 0.0,
+    })
+}
+
+/// The video's details, summary and transcript `passages`, sent as the
+/// stable (cacheable) context of every chat turn. Passages past
+/// `CHAT_CONTEXT_MAX_TOKENS` are left out and the cut is noted.
+fn chat_context(entry: &LibraryEntry, passages: &[TranscriptSegment]) -> String {
+    let info = &entry.item.video_info;
+    let mut budget = CHAT_CONTEXT_MAX_TOKENS;
+    let mut lines = Vec::new();
+    for passage in passages {
+        let line = if passage.start >= 0.0 {
+            format!("[{}] {}", format_timestamp(passage.start), passage.text)
+        } else {
+            passage.text.clone()
+        };
+        let tokens = estimate_tokens(&line);
+        if tokens > budget {
+            lines.push("[…transcripción recortada…]".to_string());
+            break;
+        }
+        budget -= tokens;
+        lines.push(line);
+    }
+    format!(
+        "Video: \"{}\"\nCanal: {}\nURL: {}\n\n## Resumen\n\n{}\n\n## Transcripción\n\n{}",
+        info.title,
+        info.channel,
+        info.url,
+        entry.summary,
+        lines.join("\n")
+    )
+}
+
+/// Embed `question` and return the closest passages across the library.
+async fn retrieve_passages(
+    library: &Library,
+    settings: &ProcessSettings,
+    question: &str,
+    limit: usize,
+) -> Result<Vec<LibraryPassage>, String> {
+    let config = settings
+        .embedding_provider
+        .as_ref()
+        .ok_or("Configura un proveedor de embeddings en Ajustes para la búsqueda semántica.")?;
+    let provider = build_embedding_provider(config)?;
+    let query = embed_all(provider.as_ref(), &[question.to_string()])
+        .await?
+        .pop()
+        .ok_or("El servidor de embeddings no devolvió ningún vector.")?;
+    let model = provider.model().to_string();
+    library
+        .blocking(move |l| l.nearest_chunks(&query, &model, limit))
+        .await
+}
+
+// ─── Commands ─────────────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub async fn list_library(
+    library: State<'_, Library>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+) -> Result<Vec<LibraryItem>, String> {
+    library
+        .blocking(move |l| l.list(limit.unwrap_or(100), offset.unwrap_or(0)))
+        .await
+}
+
+/// Full-text search over titles, channels, summaries and transcripts.
+#[tauri::command]
+pub async fn search_library(
+    library: State<'_, Library>,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<SearchHit>, String> {
+    library
+        .blocking(move |l| l.search(&query, limit.unwrap_or(50)))
+        .await
+}
+
+/// Embed every video not yet indexed with the configured model. A video that
+/// fails is skipped and reported; the rest are still indexed.
+#[tauri::command]
+pub async fn index_library(
+    app: AppHandle,
+    library: State<'_, Library>,
+    settings: ProcessSettings,
+) -> Result<IndexReport, String> {
+    let config = settings
+        .embedding_provider
+        .as_ref()
+        .ok_or("Configura un proveedor de embeddings en Ajustes para la búsqueda semántica.")?;
+    let provider = build_embedding_provider(config)?;
+    let model = provider.model().to_string();
+    let ids = library.blocking(move |l| l.unindexed(&model)).await?;
+    let total = ids.len();
+
+    let mut report = IndexReport {
+        indexed: 0,
+        failed: Vec::new(),
+    };
+    for (index, id) in ids.iter().enumerate() {
+        emit_progress(
+            &app,
+            "library",
+            &format!("Indexando video {} de {}...", index + 1, total),
+            Some(index as f32 / total as f32 * 100.0),
+        );
+        match index_video(&library, provider.as_ref(), id).await {
+            Ok(()) => report.indexed += 1,
+            Err(error) => {
+                let entry_id = id.clone();
+                let title = library
+                    .blocking(move |l| l.get(&entry_id))
+                    .await
+                    .ok()
+                    .flatten()
+                    .map(|e| e.item.video_info.title)
+                    .unwrap_or_default();
+                emit_progress(
+                    &app,
+                    "library",
+                    &format!("⚠ No se pudo indexar \"{}\": {}", title, error),
+                    None,
+                );
+                report.failed.push(IndexFailure {
+                    video_id: id.clone(),
+                    title,
+                    error,
+                });
+            }
+        }
+    }
+
+    let message = if report.failed.is_empty() {
+        "Biblioteca indexada.".to_string()
+    } else {
+        format!(
+            "Biblioteca indexada; {} videos no se pudieron indexar.",
+            report.failed.len()
+        )
+    };
+    emit_progress(&app, "done", &message, Some(100.0));
+    Ok(report)
+}
+
+/// Passages closest in meaning to `query`, across all indexed videos.
+#[tauri::command]
+pub async fn semantic_search_library(
+    library: State<'_, Library>,
+    query: String,
+    settings: ProcessSettings,
+    limit: Option<usize>,
+) -> Result<Vec<LibraryPassage>, String> {
+    retrieve_passages(&library, &settings, &query, limit.unwrap_or(20)).await
+}
+
+/// Answer `question` from the most relevant transcript passages, citing the
+/// video and minute of each one.
+#[tauri::command]
+pub async fn ask_library(
+    app: AppHandle,
+    library: State<'_, Library>,
+    question: String,
+    settings: ProcessSettings,
+    top_k: Option<usize>,
+) -> Result<LibraryAnswer, String> {
+    if question.trim().is_empty() {
+        return Err("Escribe una pregunta.".to_string());
+    }
+    let provider = build_summary_provider(
+        settings.summary_provider.as_ref(),
+        &settings.anthropic_api_key,
+        &settings.summary_model,
+    )?;
+
+    emit_progress(&app, "ask", "Buscando fragmentos relevantes...", Some(10.0));
+    let sources = retrieve_passages(
+        &library,
+        &settings,
+        &question,
+        top_k.unwrap_or(ASK_DEFAULT_PASSAGES),
+    )
+    .await?;
+    if sources.is_empty() {
+        return Err(
+            "La biblioteca no tiene videos indexados. Ejecuta la indexación semántica primero."
+                .to_string(),
+        );
+    }
+
+    let context = sources
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let minute = p
+                .timestamp
+                .map(|t| format!(", minuto {}", format_timestamp(t)))
+                .unwrap_or_default();
+            format!(
+                "[{}] «{}» — {}{}\n{}",
+                i + 1,
+                p.video_info.title,
+                p.video_info.channel,
+                minute,
+                p.text
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    emit_progress(
+        &app,
+        "ask",
+        &format!("Consultando {}...", provider.name()),
+        Some(40.0),
+    );
+    let reply = provider
+        .complete(
+            ASK_SYSTEM_PROMPT,
+            &format!("Fragmentos:\n\n{}\n\nPregunta: {}", context, question.trim()),
+            ASK_MAX_TOKENS,
+        )
+        .await?;
+
+    emit_progress(&app, "done", "Respuesta lista.", Some(100.0));
+    Ok(LibraryAnswer {
+        answer: reply.text,
+        cost_usd: provider.cost_usd(reply.input_tokens, reply.output_tokens),
+        provider: provider.name().to_string(),
+        input_tokens: reply.input_tokens,
+        output_tokens: reply.output_tokens,
+        sources,
+    })
+}
+
+/// Continue the conversation about library video `video_id` with the new
+/// `messages` (usually a single user turn). The history is stored per video
+/// and the video's summary and transcript are sent as cached context.
+#[tauri::command]
+pub async fn chat_with_video(
+    app: AppHandle,
+    library: State<'_, Library>,
+    video_id: String,
+    messages: Vec<ChatMessage>,
+    settings: ProcessSettings,
+) -> Result<ChatResult, String> {
+    if messages
+        .iter()
+        .any(|m| m.role != "user" && m.role != "assistant")
+    {
+        return Err("Los mensajes deben tener rol \"user\" o \"assistant\".".to_string());
+    }
+    if messages
+        .last()
+        .is_none_or(|m| m.role != "user" || m.content.trim().is_empty())
+    {
+        return Err("Escribe una pregunta.".to_string());
+    }
+
+    let id = video_id.clone();
+    let entry = library
+        .blocking(move |l| l.get(&id))
+        .await?
+        .ok_or("El video no está en la biblioteca.")?;
+    let provider = build_summary_provider(
+        settings.summary_provider.as_ref(),
+        &settings.anthropic_api_key,
+        &settings.summary_model,
+    )?;
+
+    let id = video_id.clone();
+    let mut conversation = library.blocking(move |l| l.chat(&id)).await?.messages;
+    conversation.extend(messages.iter().cloned());
+
+    emit_progress(
+        &app,
+        "chat",
+        &format!("Consultando {}...", provider.name()),
+        None,
+    );
+    let reply = provider
+        .chat(
+            CHAT_SYSTEM_PROMPT,
+            &chat_context(&entry, &transcript_passages(&entry)),
+            &conversation,
+            CHAT_MAX_TOKENS,
+        )
+        .await?;
+
+    let input_tokens = reply.total_input_tokens();
+    let result = ChatResult {
+        cost_usd: provider.reply_cost(&reply),
+        provider: provider.name().to_string(),
+        input_tokens,
+        output_tokens: reply.output_tokens,
+        total_tokens: input_tokens + reply.output_tokens,
+        cached_tokens: reply.cache_read_tokens,
+        reply: reply.text,
+    };
+    let reply = result.clone();
+    library
+        .blocking(move |l| l.append_chat(&video_id, &messages, &reply))
+        .await?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn get_video_chat(
+    library: State<'_, Library>,
+    video_id: String,
+) -> Result<VideoChat, String> {
+    library.blocking(move |l| l.chat(&video_id)).await
+}
+
+#[tauri::command]
+pub async fn clear_video_chat(library: State<'_, Library>, video_id: String) -> Result<(), String> {
+    library.blocking(move |l| l.clear_chat(&video_id)).await
+}
+
+#[tauri::command]
+pub async fn get_library_entry(
+    library: State<'_, Library>,
+    id: String,
+) -> Result<LibraryEntry, String> {
+    library
+        .blocking(move |l| l.get(&id))
+        .await?
+        .ok_or_else(|| "El video no está en la biblioteca.".to_string())
+}
+
+#[tauri::command]
+pub async fn delete_library_entry(library: State<'_, Library>, id: String) -> Result<(), String> {
+    if library.blocking(move |l| l.delete(&id)).await? {
+        Ok(())
+    } else {
+        Err("El video no está en la biblioteca.".to_string())
+    }
+}
+
+/// Export a stored video again (Markdown and/or Notion, as enabled in
+/// `settings`) without re-downloading, re-transcribing or re-summarizing.
+#[tauri::command]
+pub async fn reexport_library_entry(
+    app: AppHandle,
+    library: State<'_, Library>,
+    id: String,
+    settings: ProcessSettings,
+) -> Result<LibraryEntry, String> {
+    let entry_id = id.clone();
+    let entry = library
+        .blocking(move |l| l.get(&entry_id))
+        .await?
+        .ok_or("El video no está en la biblioteca.")?;
+
+    let summary = SummaryResult {
+        summary: entry.summary.clone(),
+        input_tokens: entry.input_tokens,
+        output_tokens: entry.output_tokens,
+        total_tokens: entry.item.tokens_used,
+        cost_usd: entry.item.cost_usd,
+        provider: entry.summary_provider.clone(),
+        structured: entry.structured.clone(),
+    };
+    let transcript = Transcript {
+        text: entry.transcript.clone(),
+        language: entry.item.language.clone(),
+        segments: entry.segments.clone(),
+        cost_usd: entry.item.transcription_cost_usd,
+    };
+
+    let (saved_path, notion_url) =
+        export_outputs(&app, &settings, &entry.item.video_info, &summary, &transcript).await?;
+    emit_progress(&app, "done", "Exportación completada.", Some(100.0));
+
+    library
+        .blocking(move |l| {
+            l.add_exports(&id, saved_path.as_deref(), notion_url.as_deref())?;
+            l.get(&id)
+        })
+        .await?
+        .ok_or_else(|| "El video no está en la biblioteca.".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn library() -> Library {
+        Library::open_path(std::path::Path::new(":memory:")).unwrap()
+    }
+
+    fn result(url: &str, transcript: &str) -> (ProcessResult, SummaryResult) {
+        let result = ProcessResult {
+            video_info: VideoInfo {
+                title: "Interés compuesto".to_string(),
+                channel: "Finanzas".to_string(),
+                duration: 600,
+                url: url.to_string(),
+                thumbnail: None,
+                description: None,
+                upload_date: None,
+            },
+            transcript: transcript.to_string(),
+            summary: "Resumen".to_string(),
+            tokens_used: 30,
+            audio_duration_seconds: 600.0,
+            cost_estimate: 0.03,
+            transcription_cost_usd: 0.02,
+            saved_path: Some("/notas/video.md".to_string()),
+            notion_url: None,
+            language: Some("es".to_string()),
+            segments: vec![TranscriptSegment {
+                start: 0.0,
+                end: 5.0,
+                text: transcript.to_string(),
+            }],
+            library_id: None,
+        };
+        let summary = SummaryResult {
+            summary: "Resumen".to_string(),
+            input_tokens: 20,
+            output_tokens: 10,
+            total_tokens: 30,
+            cost_usd: 0.01,
+            provider: "Claude".to_string(),
+            structured: None,
+        };
+        (result, summary)
+    }
+
+    #[test]
+    fn upsert_matches_url_variants_of_the_same_video() {
+        let library = library();
+        let (first, summary) = result("https://www.youtube.com/watch?v=abc123", "hola");
+        let (second, _) = result("https://youtu.be/abc123?t=42", "hola de nuevo");
+
+        let id = library.upsert(&first, &summary).unwrap();
+        assert_eq!(library.upsert(&second, &summary).unwrap(), id);
+
+        let items = library.list(10, 0).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].video_info.url, "https://youtu.be/abc123?t=42");
+        assert_eq!(items[0].transcription_cost_usd, 0.02);
+        assert_eq!(items[0].exports.len(), 2);
+        assert_eq!(
+            library.get(&id).unwrap().unwrap().transcript,
+            "hola de nuevo"
+        );
+    }
+
+    #[test]
+    fn search_escapes_stored_text_around_highlights() {
+        let library = library();
+        let (mut video, summary) = result("https://youtu.be/abc123", "uso de <script> & rust");
+        video.video_info.title = "Rust <b>\"rápido\"</b>".to_string();
+        library.upsert(&video, &summary).unwrap();
+
+        let hits = library.search("rust", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(
+            hits[0].title_highlight,
+            "<mark>Rust</mark> &lt;b&gt;&quot;rápido&quot;&lt;/b&gt;"
+        );
+
+        let hits = library.search("script", 10).unwrap();
+        assert!(hits[0]
+            .snippet
+            .contains("&lt;<mark>script</mark>&gt; &amp; rust"));
+    }
+
+    #[test]
+    fn vectors_round_trip_through_blobs() {
+        let vector = vec![0.5, -1.25, f32::MAX, 0.0];
+        let blob = vector_to_blob(&vector);
+        assert_eq!(blob.len(), 16);
+        assert_eq!(blob_to_vector(&blob), vector);
+    }
+
+    #[test]
+    fn videos_without_passages_are_not_reselected_for_indexing() {
+        let library = library();
+        let (video, summary) = result("https://youtu.be/abc123", "");
+        let id = library.upsert(&video, &summary).unwrap();
+        assert_eq!(library.unindexed("modelo").unwrap(), vec![id.clone()]);
+
+        library.replace_chunks(&id, "modelo", &[], &[]).unwrap();
+        assert!(library.unindexed("modelo").unwrap().is_empty());
+        assert_eq!(library.unindexed("otro").unwrap(), vec![id.clone()]);
+
+        library.upsert(&video, &summary).unwrap();
+        assert_eq!(library.unindexed("modelo").unwrap(), vec![id]);
+    }
+
+    #[test]
+    fn chat_context_keeps_the_transcript_within_budget() {
+        let library = library();
+        let (video, summary) = result("https://youtu.be/abc123", "hola");
+        let id = library.upsert(&video, &summary).unwrap();
+        let entry = library.get(&id).unwrap().unwrap();
+
+        let context = chat_context(&entry, &transcript_passages(&entry));
+        assert!(context.contains("[00:00] hola"));
+        assert!(!context.contains("recortada"));
+
+        let passage = TranscriptSegment {
+            start: -1.0,
+            end: -1.0,
+            text: "a".repeat(CHAT_CONTEXT_MAX_TOKENS * 3),
+        };
+        let passages = vec![passage.clone(), passage];
+        let context = chat_context(&entry, &passages);
+        assert!(context.contains("[…transcripción recortada…]"));
+        assert!(estimate_tokens(&context) < CHAT_CONTEXT_MAX_TOKENS + 100);
+    }
+}
@@ -0,0 +1,1452 @@
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use tauri::AppHandle;
+
+use crate::classify::CategoryTaxonomy;
+use crate::commands::{emit_progress, fold_text, truncate_notion, video_key, youtube_id};
+
+// ─── Property Mapping ──────────────────────────────────────────────────────────
+
+/// Data of a processed video that can be written to a database column.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotionField {
+    Title,
+    Url,
+    Channel,
+    Summary,
+    ActionItems,
+    Keywords,
+    Category,
+    UploadDate,
+    /// Length of the video in minutes.
+    Duration,
+    ProcessedAt,
+}
+
+/// Notion property types we know how to fill.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotionPropertyType {
+    Title,
+    RichText,
+    Url,
+    Select,
+    MultiSelect,
+    Date,
+    Number,
+}
+
+impl NotionPropertyType {
+    /// Name of the type in the Notion API.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NotionPropertyType::Title => "title",
+            NotionPropertyType::RichText => "rich_text",
+            NotionPropertyType::Url => "url",
+            NotionPropertyType::Select => "select",
+            NotionPropertyType::MultiSelect => "multi_select",
+            NotionPropertyType::Date => "date",
+            NotionPropertyType::Number => "number",
+        }
+    }
+}
+
+/// Write `field` to the database property named `property`, of type `property_type`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NotionPropertyMapping {
+    pub field: NotionField,
+    pub property: String,
+    #[serde(rename = "type")]
+    pub property_type: NotionPropertyType,
+}
+
+/// Columns of the database template shipped with the app, used when the
+/// user has not configured a mapping.
+pub fn default_property_mapping() -> Vec<NotionPropertyMapping> {
+    use NotionField as F;
+    use NotionPropertyType as T;
+    [
+        (F::Title, "Title", T::Title),
+        (F::Url, "URL Video", T::Url),
+        (F::Channel, "Canal YouTube", T::RichText),
+        (F::Summary, "Resumen Video", T::RichText),
+        (F::ActionItems, "Acciones_Aplicación", T::RichText),
+        (F::Keywords, "Keywords", T::MultiSelect),
+        (F::Category, "Categoría", T::Select),
+        (F::UploadDate, "Fecha Video", T::Date),
+    ]
+    .into_iter()
+    .map(|(field, property, property_type)| NotionPropertyMapping {
+        field,
+        property: property.to_string(),
+        property_type,
+    })
+    .collect()
+}
+
+/// The configured mapping, or the default one when none is set.
+pub fn effective_mapping(mapping: Option<&[NotionPropertyMapping]>) -> Vec<NotionPropertyMapping> {
+    match mapping {
+        Some(mapping) if !mapping.is_empty() => mapping.to_vec(),
+        _ => default_property_mapping(),
+    }
+}
+
+// ─── Property Values ───────────────────────────────────────────────────────────
+
+/// Everything `send_to_notion` knows about a video, ready to be mapped.
+pub struct NotionFieldValues {
+    pub title: String,
+    pub url: String,
+    pub channel: String,
+    pub summary: String,
+    pub action_items: String,
+    pub keywords: Vec<String>,
+    pub category: String,
+    pub upload_date: Option<String>,
+    pub duration_seconds: u64,
+    pub processed_at: String,
+}
+
+impl NotionFieldValues {
+    fn text(&self, field: NotionField) -> String {
+        match field {
+            NotionField::Title => self.title.clone(),
+            NotionField::Url => self.url.clone(),
+            NotionField::Channel => self.channel.clone(),
+            NotionField::Summary => self.summary.clone(),
+            NotionField::ActionItems => self.action_items.clone(),
+            NotionField::Keywords => self.keywords.join(", "),
+            NotionField::Category => self.category.clone(),
+            NotionField::UploadDate => self.upload_date.clone().unwrap_or_default(),
+            NotionField::Duration => format!("{} min", self.minutes()),
+            NotionField::ProcessedAt => self.processed_at.clone(),
+        }
+    }
+
+    fn list(&self, field: NotionField) -> Vec<String> {
+        match field {
+            NotionField::Keywords => self.keywords.clone(),
+            other => vec![self.text(other)],
+        }
+    }
+
+    fn date(&self, field: NotionField) -> Option<String> {
+        match field {
+            NotionField::UploadDate => self.upload_date.clone(),
+            NotionField::ProcessedAt => Some(self.processed_at.clone()),
+            _ => None,
+        }
+    }
+
+    fn number(&self, field: NotionField) -> Option<f64> {
+        match field {
+            NotionField::Duration => Some(self.minutes()),
+            other => self.text(other).trim().parse().ok(),
+        }
+    }
+
+    fn minutes(&self) -> f64 {
+        (self.duration_seconds as f64 / 60.0 * 10.0).round() / 10.0
+    }
+}
+
+/// Select option names may not contain commas and are limited to 100 characters.
+fn select_name(name: &str) -> String {
+    let name = name.replace(',', " ");
+    truncate_notion(&name.split_whitespace().collect::<Vec<_>>().join(" "), 100)
+}
+
+/// JSON of one property, or `None` when there is nothing to write.
+fn property_value(
+    property_type: NotionPropertyType,
+    field: NotionField,
+    values: &NotionFieldValues,
+) -> Option<serde_json::Value> {
+    let rich_text = |text: String| serde_json::json!([{ "text": { "content": text } }]);
+    match property_type {
+        NotionPropertyType::Title => Some(
+            serde_json::json!({ "title": rich_text(truncate_notion(&values.text(field), 2000)) }),
+        ),
+        NotionPropertyType::RichText => {
+            let text = values.text(field);
+            (!text.trim().is_empty()).then(
+                || serde_json::json!({ "rich_text": rich_text(truncate_notion(&text, 2000)) }),
+            )
+        }
+        NotionPropertyType::Url => {
+            let url = values.text(field);
+            url.starts_with("http")
+                .then(|| serde_json::json!({ "url": url }))
+        }
+        NotionPropertyType::Select => {
+            let name = select_name(&values.list(field).into_iter().next().unwrap_or_default());
+            (!name.is_empty()).then(|| serde_json::json!({ "select": { "name": name } }))
+        }
+        NotionPropertyType::MultiSelect => {
+            let options: Vec<serde_json::Value> = values
+                .list(field)
+                .iter()
+                .map(|name| select_name(name))
+                .filter(|name| !name.is_empty())
+                .map(|name| serde_json::json!({ "name": name }))
+                .collect();
+            (!options.is_empty()).then(|| serde_json::json!({ "multi_select": options }))
+        }
+        NotionPropertyType::Date => values
+            .date(field)
+            .filter(|d| !d.is_empty())
+            .map(|start| serde_json::json!({ "date": { "start": start } })),
+        NotionPropertyType::Number => values
+            .number(field)
+            .map(|number| serde_json::json!({ "number": number })),
+    }
+}
+
+/// Build the `properties` object of a new page from `mapping`.
+pub fn build_properties(
+    mapping: &[NotionPropertyMapping],
+    values: &NotionFieldValues,
+) -> Result<serde_json::Value, String> {
+    let titles = mapping
+        .iter()
+        .filter(|m| m.property_type == NotionPropertyType::Title)
+        .count();
+    if titles != 1 {
+        return Err(
+            "El mapeo de Notion debe tener exactamente una propiedad de tipo \"title\"."
+                .to_string(),
+        );
+    }
+
+    let mut properties = serde_json::Map::new();
+    for entry in mapping {
+        if entry.property.trim().is_empty() {
+            return Err(format!(
+                "Falta el nombre de la propiedad de Notion para el campo {:?}.",
+                entry.field
+            ));
+        }
+        if let Some(value) = property_value(entry.property_type, entry.field, values) {
+            properties.insert(entry.property.clone(), value);
+        }
+    }
+    Ok(serde_json::Value::Object(properties))
+}
+
+// ─── Database Inspection ───────────────────────────────────────────────────────
+
+pub(crate) const NOTION_API_URL: &str = "https://api.notion.com/v1";
+pub(crate) const NOTION_VERSION: &str = "2022-06-28";
+
+/// A column of a Notion database.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NotionDatabaseProperty {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub property_type: String,
+    /// Options of `select` and `multi_select` columns.
+    pub options: Vec<String>,
+}
+
+/// Columns of a database and everything that would make an export fail.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NotionDatabaseReport {
+    pub title: String,
+    pub properties: Vec<NotionDatabaseProperty>,
+    /// Human-readable mismatches between the database and the mapping.
+    pub problems: Vec<String>,
+}
+
+/// Fetch a database through the "retrieve database" endpoint.
+async fn retrieve_database(
+    client: &reqwest::Client,
+    api_key: &str,
+    database_id: &str,
+) -> Result<serde_json::Value, String> {
+    let response = client
+        .get(format!(
+            "{}/databases/{}",
+            NOTION_API_URL,
+            database_id.trim()
+        ))
+        .bearer_auth(api_key)
+        .header("Notion-Version", NOTION_VERSION)
+        .send()
+        .await
+        .map_err(|e| format!("Error conectando con Notion: {}", e))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        let msg = match status.as_u16() {
+            401 => "API key de Notion inválida.".to_string(),
+            404 => "Database ID no encontrado. Verifica que la base de datos está compartida con tu integración.".to_string(),
+            400 => "El Database ID no es válido o no corresponde a una base de datos.".to_string(),
+            _ => format!("Error de Notion ({}): {}", status, body),
+        };
+        return Err(msg);
+    }
+
+    response
+        .json()
+        .await
+        .map_err(|e| format!("Error parseando respuesta de Notion: {}", e))
+}
+
+fn database_properties(database: &serde_json::Value) -> Vec<NotionDatabaseProperty> {
+    let mut properties: Vec<NotionDatabaseProperty> = database["properties"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(name, property)| {
+            let property_type = property["type"].as_str().unwrap_or("").to_string();
+            let options = property[property_type.as_str()]["options"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|o| o["name"].as_str().map(str::to_string))
+                .collect();
+            NotionDatabaseProperty {
+                name: name.clone(),
+                property_type,
+                options,
+            }
+        })
+        .collect();
+    properties.sort_by(|a, b| a.name.cmp(&b.name));
+    properties
+}
+
+/// Compare `mapping` with the columns of the database; `categories` are the
+/// options the category column needs.
+pub fn check_mapping(
+    properties: &[NotionDatabaseProperty],
+    mapping: &[NotionPropertyMapping],
+    categories: &[String],
+) -> Vec<String> {
+    let mut problems = Vec::new();
+    for entry in mapping {
+        let expected = entry.property_type.as_str();
+        let Some(column) = properties.iter().find(|p| p.name == entry.property) else {
+            // Names are case- and accent-sensitive; point at a near miss.
+            let wanted = fold_text(&entry.property);
+            let hint = properties
+                .iter()
+                .find(|p| fold_text(&p.name) == wanted)
+                .map(|p| format!(" ¿Quizás \"{}\"?", p.name))
+                .unwrap_or_default();
+            problems.push(format!(
+                "Falta la columna \"{}\" (tipo {}) en la base de datos.{}",
+                entry.property, expected, hint
+            ));
+            continue;
+        };
+
+        if column.property_type != expected {
+            problems.push(format!(
+                "La columna \"{}\" es de tipo {}, pero el mapeo espera {}.",
+                column.name, column.property_type, expected
+            ));
+            continue;
+        }
+
+        // Category values are known in advance, so missing options can be reported.
+        if entry.field == NotionField::Category
+            && matches!(
+                entry.property_type,
+                NotionPropertyType::Select | NotionPropertyType::MultiSelect
+            )
+        {
+            let missing: Vec<&str> = categories
+                .iter()
+                .map(String::as_str)
+                .filter(|c| !column.options.iter().any(|o| o == c))
+                .collect();
+            if !missing.is_empty() {
+                problems.push(format!(
+                    "La columna \"{}\" no tiene las opciones: {}.",
+                    column.name,
+                    missing.join(", ")
+                ));
+            }
+        }
+    }
+    problems
+}
+
+/// Retrieve the database and check it against `mapping`.
+pub(crate) async fn inspect_database(
+    client: &reqwest::Client,
+    api_key: &str,
+    database_id: &str,
+    mapping: &[NotionPropertyMapping],
+    categories: &[String],
+) -> Result<NotionDatabaseReport, String> {
+    let database = retrieve_database(client, api_key, database_id).await?;
+    let properties = database_properties(&database);
+    let title = database["title"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|t| t["plain_text"].as_str())
+        .collect::<String>();
+
+    Ok(NotionDatabaseReport {
+        title,
+        problems: check_mapping(&properties, mapping, categories),
+        properties,
+    })
+}
+
+// ─── Commands ─────────────────────────────────────────────────────────────────
+
+/// List the columns of a database and report where they do not match the
+/// configured (or default) property mapping. Meant to run before the first export.
+#[tauri::command]
+pub async fn inspect_notion_database(
+    api_key: String,
+    database_id: String,
+    property_mapping: Option<Vec<NotionPropertyMapping>>,
+    category_taxonomy: Option<CategoryTaxonomy>,
+) -> Result<NotionDatabaseReport, String> {
+    if api_key.trim().is_empty() || database_id.trim().is_empty() {
+        return Err("Falta la API key o el Database ID de Notion.".to_string());
+    }
+    let mapping = effective_mapping(property_mapping.as_deref());
+    let categories = category_taxonomy.unwrap_or_default().names();
+    inspect_database(
+        &reqwest::Client::new(),
+        &api_key,
+        &database_id,
+        &mapping,
+        &categories,
+    )
+    .await
+}
+
+// ─── Existing Pages ────────────────────────────────────────────────────────────
+
+/// What to do when the database already has a page for the video's URL.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum NotionExistingPagePolicy {
+    /// Leave the existing page untouched and return its URL.
+    Skip,
+    /// Update the page's properties and replace its content.
+    #[default]
+    Overwrite,
+    /// Create another page, numbering the title as a new version.
+    NewVersion,
+}
+
+/// A page found in the database.
+#[derive(Debug, Clone)]
+pub struct NotionPage {
+    pub id: String,
+    pub url: String,
+    pub title: String,
+}
+
+/// Turn a non-success Notion response into an error message.
+async fn response_error(response: reqwest::Response) -> String {
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    match status.as_u16() {
+        401 => "API key de Notion inválida.".to_string(),
+        _ => format!("Error de Notion ({}): {}", status, body),
+    }
+}
+
+/// Text of a URL, rich text or title property of a page.
+fn property_text(property: &serde_json::Value) -> String {
+    match property["type"].as_str() {
+        Some("url") => property["url"].as_str().unwrap_or("").to_string(),
+        Some(kind) => property[kind]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|t| t["plain_text"].as_str())
+            .collect(),
+        None => String::new(),
+    }
+}
+
+/// Body of a database query for pages whose `property` (of filter type
+/// `condition`) contains `needle`, newest first.
+fn page_query_body(
+    property: &str,
+    condition: &str,
+    needle: &str,
+    cursor: Option<&str>,
+) -> serde_json::Value {
+    let mut body = serde_json::json!({
+        "filter": { "property": property, condition: { "contains": needle } },
+        "sorts": [{ "timestamp": "created_time", "direction": "descending" }],
+        "page_size": NOTION_MAX_CHILDREN,
+    });
+    if let Some(cursor) = cursor {
+        body["start_cursor"] = serde_json::json!(cursor);
+    }
+    body
+}
+
+/// Cursor of the next page of a paginated Notion list, if any.
+fn next_cursor(json: &serde_json::Value) -> Option<String> {
+    json["next_cursor"]
+        .as_str()
+        .filter(|_| json["has_more"].as_bool().unwrap_or(false))
+        .map(str::to_string)
+}
+
+/// Pages of a query response whose `property` links to the video `key`,
+/// skipping archived ones.
+fn matching_pages(json: &serde_json::Value, property: &str, key: &str) -> Vec<NotionPage> {
+    json["results"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|page| !page["archived"].as_bool().unwrap_or(false))
+        .filter(|page| video_key(&property_text(&page["properties"][property])) == key)
+        .filter_map(|page| {
+            let title = page["properties"]
+                .as_object()
+                .into_iter()
+                .flat_map(|p| p.values())
+                .find(|p| p["type"] == "title")
+                .map(property_text)
+                .unwrap_or_default();
+            Some(NotionPage {
+                id: page["id"].as_str()?.to_string(),
+                url: page["url"].as_str().unwrap_or("").to_string(),
+                title,
+            })
+        })
+        .collect()
+}
+
+/// Number for a new version of a page: one past the highest "(versión N)"
+/// among `pages`, where a page without the suffix counts as version 1.
+pub(crate) fn next_version(pages: &[NotionPage]) -> u32 {
+    pages
+        .iter()
+        .map(|page| {
+            page.title
+                .trim_end()
+                .strip_suffix(')')
+                .and_then(|t| t.rsplit_once("(versión "))
+                .and_then(|(_, n)| n.trim().parse::<u32>().ok())
+                .unwrap_or(1)
+        })
+        .max()
+        .unwrap_or(0)
+        + 1
+}
+
+/// Why a database query failed: Notion answered with an error status, or the
+/// request never got an answer.
+#[derive(Debug)]
+pub(crate) enum QueryError {
+    Status(u16, String),
+    Request(String),
+}
+
+impl From<String> for QueryError {
+    fn from(message: String) -> Self {
+        QueryError::Request(message)
+    }
+}
+
+/// Pages of the database whose mapped URL property points to the same video
+/// as `url` (any variant of a YouTube link), most recently created first.
+/// Empty when the mapping has no URL column.
+pub(crate) async fn find_pages_by_url(
+    client: &reqwest::Client,
+    api_key: &str,
+    database_id: &str,
+    mapping: &[NotionPropertyMapping],
+    url: &str,
+) -> Result<Vec<NotionPage>, QueryError> {
+    let Some(column) = mapping.iter().find(|m| m.field == NotionField::Url) else {
+        return Ok(Vec::new());
+    };
+    let condition = match column.property_type {
+        NotionPropertyType::Url => "url",
+        NotionPropertyType::RichText => "rich_text",
+        NotionPropertyType::Title => "title",
+        _ => return Ok(Vec::new()),
+    };
+    if url.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Notion can only match text, so fetch pages mentioning the video id and
+    // compare the normalized links here.
+    let key = video_key(url);
+    let needle = youtube_id(url).unwrap_or_else(|| key.clone());
+    let mut pages = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let body = page_query_body(&column.property, condition, &needle, cursor.as_deref());
+        let response = send_with_retry(
+            client
+                .post(format!(
+                    "{}/databases/{}/query",
+                    NOTION_API_URL,
+                    database_id.trim()
+                ))
+                .bearer_auth(api_key)
+                .header("Notion-Version", NOTION_VERSION)
+                .json(&body),
+        )
+        .await?;
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let body = response.text().await.unwrap_or_default();
+            return Err(QueryError::Status(status, body));
+        }
+        let json: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| format!("Error parseando respuesta de Notion: {}", e))?;
+
+        pages.extend(matching_pages(&json, &column.property, &key));
+        cursor = next_cursor(&json);
+        if cursor.is_none() {
+            break;
+        }
+    }
+    Ok(pages)
+}
+
+/// Overwrite fields of an existing page (`properties`, `cover`, `icon`…).
+pub(crate) async fn update_page(
+    client: &reqwest::Client,
+    api_key: &str,
+    page_id: &str,
+    fields: &serde_json::Value,
+) -> Result<(), String> {
+    let response = send_with_retry(
+        client
+            .patch(format!("{}/pages/{}", NOTION_API_URL, page_id))
+            .bearer_auth(api_key)
+            .header("Notion-Version", NOTION_VERSION)
+            .json(fields),
+    )
+    .await?;
+    if !response.status().is_success() {
+        return Err(response_error(response).await);
+    }
+    Ok(())
+}
+
+/// Ids of the blocks in a "retrieve block children" response.
+fn block_ids(json: &serde_json::Value) -> Vec<String> {
+    json["results"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|b| b["id"].as_str().map(str::to_string))
+        .collect()
+}
+
+/// Ids of every top-level block of a page, following pagination.
+pub(crate) async fn page_block_ids(
+    client: &reqwest::Client,
+    api_key: &str,
+    page_id: &str,
+) -> Result<Vec<String>, String> {
+    let mut ids = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let mut request = client
+            .get(format!("{}/blocks/{}/children", NOTION_API_URL, page_id))
+            .bearer_auth(api_key)
+            .header("Notion-Version", NOTION_VERSION)
+            .query(&[("page_size", NOTION_MAX_CHILDREN.to_string())]);
+        if let Some(cursor) = &cursor {
+            request = request.query(&[("start_cursor", cursor)]);
+        }
+        let response = send_with_retry(request).await?;
+        if !response.status().is_success() {
+            return Err(response_error(response).await);
+        }
+        let json: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| format!("Error parseando respuesta de Notion: {}", e))?;
+
+        ids.extend(block_ids(&json));
+        cursor = next_cursor(&json);
+        if cursor.is_none() {
+            break;
+        }
+    }
+    Ok(ids)
+}
+
+/// Delete blocks by id; nested blocks go with their parent.
+pub(crate) async fn delete_blocks(
+    client: &reqwest::Client,
+    api_key: &str,
+    ids: &[String],
+) -> Result<(), String> {
+    for id in ids {
+        let response = send_with_retry(
+            client
+                .delete(format!("{}/blocks/{}", NOTION_API_URL, id))
+                .bearer_auth(api_key)
+                .header("Notion-Version", NOTION_VERSION),
+        )
+        .await?;
+        if !response.status().is_success() {
+            return Err(response_error(response).await);
+        }
+    }
+    Ok(())
+}
+
+// ─── Page Media ────────────────────────────────────────────────────────────────
+
+/// Block showing the video itself at the top of the page.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotionVideoBlock {
+    /// Playable embed (YouTube and Vimeo links).
+    Video,
+    /// Link preview card with title and thumbnail.
+    Bookmark,
+}
+
+/// External-file cover for a page, when `thumbnail` is an http(s) URL.
+pub fn page_cover(thumbnail: Option<&str>) -> Option<serde_json::Value> {
+    let url = thumbnail?.trim();
+    if !(url.starts_with("https://") || url.starts_with("http://")) {
+        return None;
+    }
+    Some(serde_json::json!({ "type": "external", "external": { "url": url } }))
+}
+
+/// Block of kind `kind` pointing at `url`.
+pub fn video_block(kind: NotionVideoBlock, url: &str) -> serde_json::Value {
+    match kind {
+        NotionVideoBlock::Video => serde_json::json!({
+            "object": "block",
+            "type": "video",
+            "video": { "type": "external", "external": { "url": url } }
+        }),
+        NotionVideoBlock::Bookmark => serde_json::json!({
+            "object": "block",
+            "type": "bookmark",
+            "bookmark": { "url": url }
+        }),
+    }
+}
+
+// ─── Block Upload ──────────────────────────────────────────────────────────────
+
+/// Notion rejects any request with more than 100 blocks in a children array.
+const NOTION_MAX_CHILDREN: usize = 100;
+/// Retries of a request answered with 429 (rate limited).
+const NOTION_MAX_RETRIES: u32 = 3;
+
+/// Nested `children` uploaded in their own requests. Tables must be created
+/// with their rows, so only rows past the first batch are appended later.
+fn nested_children(block: &serde_json::Value) -> Option<&[serde_json::Value]> {
+    let kind = block["type"].as_str()?;
+    let children = block[kind]["children"].as_array()?;
+    let deferred = if kind == "table" {
+        children.get(NOTION_MAX_CHILDREN..)?
+    } else {
+        children.as_slice()
+    };
+    Some(deferred).filter(|c| !c.is_empty())
+}
+
+/// Remove and return the nested `children` of a block, so every request
+/// carries a single level and never hits the nesting limit. A table keeps
+/// its first batch of rows.
+fn take_children(block: &mut serde_json::Value) -> Vec<serde_json::Value> {
+    let Some(kind) = block["type"].as_str().map(str::to_string) else {
+        return Vec::new();
+    };
+    let Some(body) = block.get_mut(&kind).and_then(|b| b.as_object_mut()) else {
+        return Vec::new();
+    };
+    if kind == "table" {
+        return match body.get_mut("children") {
+            Some(serde_json::Value::Array(rows)) if rows.len() > NOTION_MAX_CHILDREN => {
+                rows.split_off(NOTION_MAX_CHILDREN)
+            }
+            _ => Vec::new(),
+        };
+    }
+    match body.remove("children") {
+        Some(serde_json::Value::Array(children)) => children,
+        _ => Vec::new(),
+    }
+}
+
+/// Requests needed to upload `blocks` one level and one batch at a time.
+pub(crate) fn count_requests(blocks: &[serde_json::Value]) -> usize {
+    blocks.len().div_ceil(NOTION_MAX_CHILDREN)
+        + blocks
+            .iter()
+            .filter_map(nested_children)
+            .map(count_requests)
+            .sum::<usize>()
+}
+
+/// Split `blocks` into the batch sent with the page itself (leading blocks
+/// without children, at most one batch) and the rest, appended afterwards.
+pub(crate) fn split_first_batch(
+    mut blocks: Vec<serde_json::Value>,
+) -> (Vec<serde_json::Value>, Vec<serde_json::Value>) {
+    let flat = blocks
+        .iter()
+        .take(NOTION_MAX_CHILDREN)
+        .take_while(|b| nested_children(b).is_none())
+        .count();
+    let rest = blocks.split_off(flat);
+    (blocks, rest)
+}
+
+/// Send a request to Notion, waiting and retrying when rate limited.
+pub(crate) async fn send_with_retry(
+    request: reqwest::RequestBuilder,
+) -> Result<reqwest::Response, String> {
+    let mut attempt = 0;
+    loop {
+        let current = request
+            .try_clone()
+            .ok_or("No se pudo repetir la solicitud a Notion.")?;
+        let response = current
+            .send()
+            .await
+            .map_err(|e| format!("Error conectando con Notion: {}", e))?;
+        if response.status().as_u16() != 429 || attempt >= NOTION_MAX_RETRIES {
+            return Ok(response);
+        }
+        let wait = response
+            .headers()
+            .get("retry-after")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(1);
+        tokio::time::sleep(std::time::Duration::from_secs(wait)).await;
+        attempt += 1;
+    }
+}
+
+/// Append `blocks` under `parent_id` through the "append block children"
+/// endpoint, in batches of 100 and one nesting level per request. `done` and
+/// `total` count requests for progress reporting.
+pub(crate) async fn append_blocks(
+    app: &AppHandle,
+    client: &reqwest::Client,
+    api_key: &str,
+    parent_id: &str,
+    blocks: Vec<serde_json::Value>,
+    mut done: usize,
+    total: usize,
+) -> Result<(), String> {
+    let mut pending = VecDeque::from([(parent_id.to_string(), blocks)]);
+
+    while let Some((parent, blocks)) = pending.pop_front() {
+        let mut blocks = blocks.into_iter().peekable();
+        while blocks.peek().is_some() {
+            let mut batch: Vec<serde_json::Value> =
+                blocks.by_ref().take(NOTION_MAX_CHILDREN).collect();
+            let nested: Vec<Vec<serde_json::Value>> = batch.iter_mut().map(take_children).collect();
+
+            done += 1;
+            emit_progress(
+                app,
+                "notion",
+                &format!(
+                    "Enviando contenido a Notion (lote {} de {})...",
+                    done, total
+                ),
+                Some(done as f32 / total as f32 * 100.0),
+            );
+
+            let response = send_with_retry(
+                client
+                    .patch(format!("{}/blocks/{}/children", NOTION_API_URL, parent))
+                    .bearer_auth(api_key)
+                    .header("Notion-Version", NOTION_VERSION)
+                    .json(&serde_json::json!({ "children": batch })),
+            )
+            .await?;
+
+            if !response.status().is_success() {
+                let status = response.status();
+                let body = response.text().await.unwrap_or_default();
+                return Err(format!("Error de Notion ({}): {}", status, body));
+            }
+
+            let json: serde_json::Value = response
+                .json()
+                .await
+                .map_err(|e| format!("Error parseando respuesta de Notion: {}", e))?;
+
+            // Results are the new blocks in order; their children go next.
+            let created = json["results"].as_array().cloned().unwrap_or_default();
+            for (block, children) in created.iter().zip(nested) {
+                if children.is_empty() {
+                    continue;
+                }
+                let id = block["id"]
+                    .as_str()
+                    .ok_or("Respuesta inesperada de Notion")?;
+                pending.push_back((id.to_string(), children));
+            }
+        }
+    }
+    Ok(())
+}
+
+// ─── Markdown ──────────────────────────────────────────────────────────────────
+
+/// Characters allowed in a single Notion rich text object.
+const NOTION_MAX_TEXT: usize = 2000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Annotations {
+    bold: bool,
+    italic: bool,
+    strikethrough: bool,
+    code: bool,
+}
+
+/// A run of text sharing the same formatting and link.
+#[derive(Debug, Clone)]
+struct TextRun {
+    text: String,
+    style: Annotations,
+    link: Option<String>,
+}
+
+/// Convert runs to Notion rich text, splitting at the 2000-character limit.
+fn rich_text(runs: &[TextRun]) -> Vec<serde_json::Value> {
+    let mut objects = Vec::new();
+    for run in runs {
+        let chars: Vec<char> = run.text.chars().collect();
+        for chunk in chars.chunks(NOTION_MAX_TEXT) {
+            let mut text = serde_json::json!({ "content": chunk.iter().collect::<String>() });
+            if let Some(url) = &run.link {
+                text["link"] = serde_json::json!({ "url": url });
+            }
+            let mut object = serde_json::json!({ "type": "text", "text": text });
+            if run.style != Annotations::default() {
+                object["annotations"] = serde_json::json!({
+                    "bold": run.style.bold,
+                    "italic": run.style.italic,
+                    "strikethrough": run.style.strikethrough,
+                    "code": run.style.code,
+                });
+            }
+            objects.push(object);
+        }
+    }
+    objects
+}
+
+/// Languages accepted by Notion code blocks for the usual fence tags.
+fn code_language(tag: &str) -> &'static str {
+    match tag.trim().to_lowercase().as_str() {
+        "rust" | "rs" => "rust",
+        "python" | "py" => "python",
+        "javascript" | "js" => "javascript",
+        "typescript" | "ts" => "typescript",
+        "json" => "json",
+        "bash" => "bash",
+        "sh" | "shell" | "zsh" => "shell",
+        "powershell" | "ps1" => "powershell",
+        "html" => "html",
+        "css" => "css",
+        "sql" => "sql",
+        "java" => "java",
+        "kotlin" => "kotlin",
+        "go" => "go",
+        "c" => "c",
+        "cpp" | "c++" => "c++",
+        "csharp" | "cs" | "c#" => "c#",
+        "php" => "php",
+        "ruby" | "rb" => "ruby",
+        "swift" => "swift",
+        "yaml" | "yml" => "yaml",
+        "toml" => "toml",
+        "xml" => "xml",
+        "markdown" | "md" => "markdown",
+        "latex" | "tex" => "latex",
+        _ => "plain text",
+    }
+}
+
+/// A block that can hold nested blocks while its Markdown is being read.
+struct Container {
+    kind: &'static str,
+    /// Text of the block itself: the first paragraph inside it.
+    runs: Option<Vec<TextRun>>,
+    checked: Option<bool>,
+    children: Vec<serde_json::Value>,
+}
+
+#[derive(Default)]
+struct TableState {
+    rows: Vec<Vec<Vec<serde_json::Value>>>,
+    row: Vec<Vec<serde_json::Value>>,
+    has_header: bool,
+}
+
+/// Turns pulldown-cmark events into Notion blocks.
+#[derive(Default)]
+struct BlockBuilder {
+    blocks: Vec<serde_json::Value>,
+    containers: Vec<Container>,
+    /// Open lists, innermost last; `true` when ordered.
+    lists: Vec<bool>,
+    runs: Vec<TextRun>,
+    style: Annotations,
+    link: Option<String>,
+    /// Language and text of the fenced code block being read.
+    code: Option<(String, String)>,
+    table: Option<TableState>,
+}
+
+fn text_block(kind: &str, runs: &[TextRun], children: Vec<serde_json::Value>) -> serde_json::Value {
+    let mut body = serde_json::json!({ "rich_text": rich_text(runs) });
+    if !children.is_empty() {
+        body["children"] = serde_json::Value::Array(children);
+    }
+    serde_json::json!({ "object": "block", "type": kind, kind: body })
+}
+
+impl BlockBuilder {
+    fn emit(&mut self, block: serde_json::Value) {
+        match self.containers.last_mut() {
+            Some(container) => container.children.push(block),
+            None => self.blocks.push(block),
+        }
+    }
+
+    fn push_text(&mut self, text: &str, style: Annotations) {
+        if text.is_empty() {
+            return;
+        }
+        // Notion only links absolute URLs.
+        let link = self.link.clone().filter(|l| l.starts_with("http"));
+        match self.runs.last_mut() {
+            Some(last) if last.style == style && last.link == link => last.text.push_str(text),
+            _ => self.runs.push(TextRun {
+                text: text.to_string(),
+                style,
+                link,
+            }),
+        }
+    }
+
+    /// Close the current paragraph: it becomes the text of an enclosing list
+    /// item or quote that has none yet, or a paragraph block otherwise.
+    fn flush_paragraph(&mut self) {
+        if self.runs.is_empty() {
+            return;
+        }
+        let runs = std::mem::take(&mut self.runs);
+        match self.containers.last_mut() {
+            Some(container) if container.runs.is_none() => container.runs = Some(runs),
+            _ => self.emit(text_block("paragraph", &runs, Vec::new())),
+        }
+    }
+
+    fn open_container(&mut self, kind: &'static str) {
+        self.flush_paragraph();
+        self.containers.push(Container {
+            kind,
+            runs: None,
+            checked: None,
+            children: Vec::new(),
+        });
+    }
+
+    fn close_container(&mut self) {
+        self.flush_paragraph();
+        let Some(container) = self.containers.pop() else {
+            return;
+        };
+        let runs = container.runs.unwrap_or_default();
+        let mut block = text_block(container.kind, &runs, container.children);
+        if let Some(checked) = container.checked {
+            block[container.kind]["checked"] = serde_json::json!(checked);
+        }
+        self.emit(block);
+    }
+
+    fn handle(&mut self, event: Event) {
+        if let Some((_, code)) = self.code.as_mut() {
+            match event {
+                Event::Text(text) => return code.push_str(&text),
+                Event::End(TagEnd::CodeBlock) => {}
+                _ => return,
+            }
+        }
+
+        match event {
+            Event::Start(Tag::Paragraph) | Event::Start(Tag::Heading { .. }) => {
+                self.flush_paragraph()
+            }
+            Event::End(TagEnd::Paragraph) => self.flush_paragraph(),
+            Event::End(TagEnd::Heading(level)) => {
+                let kind = match level {
+                    HeadingLevel::H1 => "heading_1",
+                    HeadingLevel::H2 => "heading_2",
+                    _ => "heading_3",
+                };
+                let runs = std::mem::take(&mut self.runs);
+                self.emit(text_block(kind, &runs, Vec::new()));
+            }
+            Event::Start(Tag::BlockQuote(_)) => self.open_container("quote"),
+            Event::End(TagEnd::BlockQuote(_)) => self.close_container(),
+            Event::Start(Tag::List(start)) => {
+                self.flush_paragraph();
+                self.lists.push(start.is_some());
+            }
+            Event::End(TagEnd::List(_)) => {
+                self.lists.pop();
+            }
+            Event::Start(Tag::Item) => {
+                let kind = if self.lists.last() == Some(&true) {
+                    "numbered_list_item"
+                } else {
+                    "bulleted_list_item"
+                };
+                self.open_container(kind);
+            }
+            Event::End(TagEnd::Item) => self.close_container(),
+            Event::TaskListMarker(checked) => {
+                if let Some(container) = self.containers.last_mut() {
+                    container.kind = "to_do";
+                    container.checked = Some(checked);
+                }
+            }
+            Event::Start(Tag::CodeBlock(kind)) => {
+                self.flush_paragraph();
+                let language = match kind {
+                    CodeBlockKind::Fenced(tag) => tag.to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                self.code = Some((language, String::new()));
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if let Some((language, code)) = self.code.take() {
+                    let runs = [TextRun {
+                        text: code.trim_end_matches('\n').to_string(),
+                        style: Annotations::default(),
+                        link: None,
+                    }];
+                    self.emit(serde_json::json!({
+                        "object": "block",
+                        "type": "code",
+                        "code": {
+                            "rich_text": rich_text(&runs),
+                            "language": code_language(&language)
+                        }
+                    }));
+                }
+            }
+            Event::Start(Tag::Table(_)) => {
+                self.flush_paragraph();
+                self.table = Some(TableState::default());
+            }
+            Event::Start(Tag::TableHead) => {
+                if let Some(table) = self.table.as_mut() {
+                    table.has_header = true;
+                }
+            }
+            Event::End(TagEnd::TableCell) => {
+                let runs = std::mem::take(&mut self.runs);
+                if let Some(table) = self.table.as_mut() {
+                    table.row.push(rich_text(&runs));
+                }
+            }
+            Event::End(TagEnd::TableHead) | Event::End(TagEnd::TableRow) => {
+                if let Some(table) = self.table.as_mut() {
+                    let row = std::mem::take(&mut table.row);
+                    table.rows.push(row);
+                }
+            }
+            Event::End(TagEnd::Table) => {
+                if let Some(table) = self.table.take() {
+                    self.emit(table_block(table));
+                }
+            }
+            Event::Start(Tag::Emphasis) => self.style.italic = true,
+            Event::End(TagEnd::Emphasis) => self.style.italic = false,
+            Event::Start(Tag::Strong) => self.style.bold = true,
+            Event::End(TagEnd::Strong) => self.style.bold = false,
+            Event::Start(Tag::Strikethrough) => self.style.strikethrough = true,
+            Event::End(TagEnd::Strikethrough) => self.style.strikethrough = false,
+            Event::Start(Tag::Link { dest_url, .. }) => self.link = Some(dest_url.to_string()),
+            Event::End(TagEnd::Link) => self.link = None,
+            Event::Text(text) | Event::Html(text) | Event::InlineHtml(text) => {
+                self.push_text(&text, self.style)
+            }
+            Event::Code(text) => self.push_text(
+                &text,
+                Annotations {
+                    code: true,
+                    ..self.style
+                },
+            ),
+            Event::SoftBreak => self.push_text(" ", self.style),
+            Event::HardBreak => self.push_text("\n", self.style),
+            Event::Rule => {
+                self.flush_paragraph();
+                self.emit(
+                    serde_json::json!({ "object": "block", "type": "divider", "divider": {} }),
+                );
+            }
+            _ => {}
+        }
+    }
+
+    fn finish(mut self) -> Vec<serde_json::Value> {
+        while !self.containers.is_empty() {
+            self.close_container();
+        }
+        self.flush_paragraph();
+        self.blocks
+    }
+}
+
+/// A Notion table; rows are padded to the widest one.
+fn table_block(table: TableState) -> serde_json::Value {
+    let width = table.rows.iter().map(Vec::len).max().unwrap_or(1).max(1);
+    let rows: Vec<serde_json::Value> = table
+        .rows
+        .into_iter()
+        .map(|mut cells| {
+            cells.resize(width, Vec::new());
+            serde_json::json!({
+                "object": "block",
+                "type": "table_row",
+                "table_row": { "cells": cells }
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "object": "block",
+        "type": "table",
+        "table": {
+            "table_width": width,
+            "has_column_header": table.has_header,
+            "has_row_header": false,
+            "children": rows
+        }
+    })
+}
+
+/// Convert Markdown to Notion blocks: headings, paragraphs with bold,
+/// italic, strikethrough, inline code and links, bulleted, numbered and
+/// to-do lists with nesting, quotes, fenced code, tables and dividers.
+pub fn markdown_to_blocks(markdown: &str) -> Vec<serde_json::Value> {
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut builder = BlockBuilder::default();
+    for event in Parser::new_ext(markdown, options) {
+        builder.handle(event);
+    }
+    builder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Plain text of a block's rich text.
+    fn text(block: &serde_json::Value) -> String {
+        let kind = block["type"].as_str().unwrap();
+        block[kind]["rich_text"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["text"]["content"].as_str().unwrap())
+            .collect()
+    }
+
+    fn children(block: &serde_json::Value) -> &Vec<serde_json::Value> {
+        let kind = block["type"].as_str().unwrap();
+        block[kind]["children"].as_array().unwrap()
+    }
+
+    #[test]
+    fn nested_lists_become_child_blocks() {
+        let blocks = markdown_to_blocks("- uno\n  1. dos\n- tres");
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0]["type"], "bulleted_list_item");
+        assert_eq!(text(&blocks[0]), "uno");
+        let nested = children(&blocks[0]);
+        assert_eq!(nested[0]["type"], "numbered_list_item");
+        assert_eq!(text(&nested[0]), "dos");
+        assert_eq!(text(&blocks[1]), "tres");
+        assert!(blocks[1]["bulleted_list_item"].get("children").is_none());
+    }
+
+    #[test]
+    fn tight_list_items_keep_their_formatted_text() {
+        let blocks = markdown_to_blocks("- uno **dos** [tres](https://a.io)");
+        assert_eq!(blocks.len(), 1);
+        let runs = blocks[0]["bulleted_list_item"]["rich_text"]
+            .as_array()
+            .unwrap();
+        assert_eq!(runs.len(), 4);
+        assert_eq!(runs[0]["text"]["content"], "uno ");
+        assert_eq!(runs[1]["annotations"]["bold"], true);
+        assert_eq!(runs[3]["text"]["link"]["url"], "https://a.io");
+    }
+
+    #[test]
+    fn quote_takes_its_first_paragraph_as_text() {
+        let blocks = markdown_to_blocks("> primera\n>\n> segunda");
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0]["type"], "quote");
+        assert_eq!(text(&blocks[0]), "primera");
+        assert_eq!(text(&children(&blocks[0])[0]), "segunda");
+    }
+
+    #[test]
+    fn fenced_code_maps_the_language_tag() {
+        let blocks = markdown_to_blocks("```rs\nfn main() {}\n```\n\n```brainfuck\n+\n```");
+        assert_eq!(blocks[0]["code"]["language"], "rust");
+        assert_eq!(text(&blocks[0]), "fn main() {}");
+        assert_eq!(blocks[1]["code"]["language"], "plain text");
+    }
+
+    #[test]
+    fn task_list_items_become_to_dos() {
+        let blocks = markdown_to_blocks("- [x] hecho\n- [ ] pendiente");
+        assert_eq!(blocks[0]["type"], "to_do");
+        assert_eq!(blocks[0]["to_do"]["checked"], true);
+        assert_eq!(text(&blocks[0]), "hecho");
+        assert_eq!(blocks[1]["to_do"]["checked"], false);
+    }
+
+    #[test]
+    fn tables_keep_the_header_row_and_pad_cells() {
+        let blocks = markdown_to_blocks("| a | b |\n|---|---|\n| 1 |\n");
+        let table = &blocks[0]["table"];
+        assert_eq!(table["has_column_header"], true);
+        assert_eq!(table["table_width"], 2);
+        let rows = table["children"].as_array().unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["table_row"]["cells"][0][0]["text"]["content"], "a");
+        assert_eq!(rows[1]["table_row"]["cells"][1], serde_json::json!([]));
+    }
+
+    #[test]
+    fn long_text_is_split_at_the_rich_text_limit() {
+        let blocks = markdown_to_blocks(&"a".repeat(NOTION_MAX_TEXT * 2 + 1));
+        let runs = blocks[0]["paragraph"]["rich_text"].as_array().unwrap();
+        assert_eq!(runs.len(), 3);
+        assert_eq!(runs[2]["text"]["content"], "a");
+        assert_eq!(text(&blocks[0]).len(), NOTION_MAX_TEXT * 2 + 1);
+    }
+
+    #[test]
+    fn tables_over_one_batch_append_their_remaining_rows() {
+        let rows: String = (0..250).map(|i| format!("| {} |\n", i)).collect();
+        let mut blocks = markdown_to_blocks(&format!("| n |\n|---|\n{}", rows));
+        assert_eq!(count_requests(&blocks), 3);
+
+        let (first, rest) = split_first_batch(blocks.clone());
+        assert!(first.is_empty());
+        assert_eq!(rest.len(), 1);
+
+        let deferred = take_children(&mut blocks[0]);
+        assert_eq!(deferred.len(), 151);
+        let kept = blocks[0]["table"]["children"].as_array().unwrap();
+        assert_eq!(kept.len(), NOTION_MAX_CHILDREN);
+        assert_eq!(
+            deferred[0]["table_row"]["cells"][0][0]["text"]["content"],
+            "99"
+        );
+    }
+
+    fn page(id: &str, title: &str, url: &str, archived: bool) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "url": format!("https://notion.so/{}", id),
+            "archived": archived,
+            "properties": {
+                "Nombre": { "type": "title", "title": [{ "plain_text": title }] },
+                "URL": { "type": "url", "url": url }
+            }
+        })
+    }
+
+    #[test]
+    fn page_query_body_filters_by_the_column_and_carries_the_cursor() {
+        let body = page_query_body("URL", "url", "abc123", None);
+        assert_eq!(
+            body["filter"],
+            serde_json::json!({ "property": "URL", "url": { "contains": "abc123" } })
+        );
+        assert_eq!(body["page_size"], NOTION_MAX_CHILDREN);
+        assert!(body.get("start_cursor").is_none());
+
+        let body = page_query_body("Enlace", "rich_text", "abc123", Some("c2"));
+        assert_eq!(body["filter"]["rich_text"]["contains"], "abc123");
+        assert_eq!(body["start_cursor"], "c2");
+    }
+
+    #[test]
+    fn next_cursor_follows_has_more() {
+        let more = serde_json::json!({ "has_more": true, "next_cursor": "c2" });
+        assert_eq!(next_cursor(&more).as_deref(), Some("c2"));
+        let last = serde_json::json!({ "has_more": false, "next_cursor": "c2" });
+        assert_eq!(next_cursor(&last), None);
+        assert_eq!(next_cursor(&serde_json::json!({ "has_more": true })), None);
+    }
+
+    #[test]
+    fn matching_pages_keep_live_pages_of_the_same_video() {
+        let json = serde_json::json!({ "results": [
+            page("p1", "Video", "https://youtu.be/abc123", false),
+            page("p2", "Video", "https://www.youtube.com/watch?v=abc123x", false),
+            page("p3", "Video", "https://www.youtube.com/watch?v=abc123", true),
+            page("p4", "Video (versión 2)", "https://m.youtube.com/watch?v=abc123", false),
+        ]});
+        let pages = matching_pages(&json, "URL", &video_key("https://youtu.be/abc123"));
+        let ids: Vec<&str> = pages.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, ["p1", "p4"]);
+        assert_eq!(pages[1].title, "Video (versión 2)");
+        assert_eq!(pages[1].url, "https://notion.so/p4");
+    }
+
+    #[test]
+    fn block_ids_reads_every_result() {
+        let json =
+            serde_json::json!({ "results": [{ "id": "b1" }, { "type": "x" }, { "id": "b2" }] });
+        assert_eq!(block_ids(&json), ["b1", "b2"]);
+    }
+
+    #[test]
+    fn next_version_follows_the_highest_existing_number() {
+        let named = |title: &str| NotionPage {
+            id: String::new(),
+            url: String::new(),
+            title: title.to_string(),
+        };
+        assert_eq!(next_version(&[]), 1);
+        assert_eq!(next_version(&[named("Video")]), 2);
+        let pages = [
+            named("Video (versión 3)"),
+            named("Video"),
+            named("Video (versión 2)"),
+        ];
+        assert_eq!(next_version(&pages), 4);
+    }
+}
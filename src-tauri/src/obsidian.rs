@@ -0,0 +1,494 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::classify::classify_offline;
+use crate::commands::{
+    format_duration, get_default_output_dir, markdown_transcript, video_key, youtube_id,
+    ProcessSettings, SummaryResult, Transcript, VideoInfo,
+};
+use crate::tags::video_tags;
+
+// ─── Configuration ─────────────────────────────────────────────────────────────
+
+/// Layout of the exported notes inside an Obsidian vault. Folders are
+/// relative to the vault root; an empty folder means the root itself.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ObsidianExport {
+    /// Vault root; the Markdown output folder when unset.
+    pub vault_dir: Option<String>,
+    pub notes_folder: String,
+    /// Per-channel index notes.
+    pub channels_folder: String,
+    /// Downloaded thumbnails.
+    pub attachments_folder: String,
+    pub transcripts_folder: String,
+    /// Save the thumbnail in the vault instead of linking the remote image.
+    pub download_thumbnail: bool,
+}
+
+impl Default for ObsidianExport {
+    fn default() -> Self {
+        ObsidianExport {
+            vault_dir: None,
+            notes_folder: "Videos".to_string(),
+            channels_folder: "Canales".to_string(),
+            attachments_folder: "Adjuntos".to_string(),
+            transcripts_folder: "Transcripciones".to_string(),
+            download_thumbnail: true,
+        }
+    }
+}
+
+// ─── Helpers ───────────────────────────────────────────────────────────────────
+
+/// Note name without the characters Obsidian forbids in file names and links.
+fn note_name(title: &str) -> String {
+    let name: String = title
+        .chars()
+        .map(|c| match c {
+            '*' | '"' | '\\' | '/' | '<' | '>' | ':' | '|' | '?' | '#' | '^' | '[' | ']' => ' ',
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .collect();
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    let name = name.trim_matches('.').trim();
+    if name.is_empty() {
+        "Sin título".to_string()
+    } else {
+        name.chars().take(120).collect()
+    }
+}
+
+/// Lock serializing read-modify-write of one vault file, since exports run
+/// concurrently.
+fn file_lock(path: &Path) -> Arc<tokio::sync::Mutex<()>> {
+    static LOCKS: OnceLock<Mutex<HashMap<PathBuf, Arc<tokio::sync::Mutex<()>>>>> = OnceLock::new();
+    let mut locks = LOCKS.get_or_init(Default::default).lock().unwrap();
+    locks.entry(path.to_path_buf()).or_default().clone()
+}
+
+/// The `url` of a note's front matter.
+fn front_matter_url(note: &str) -> Option<String> {
+    note.strip_prefix("---\n")?
+        .lines()
+        .take_while(|line| *line != "---")
+        .find_map(|line| line.strip_prefix("url: "))
+        .and_then(|url| serde_json::from_str(url).ok())
+}
+
+/// Name for the video's note: its title, plus the video id (or a number)
+/// when a note of another video already has that name.
+async fn unique_note_name(notes_dir: &Path, video_info: &VideoInfo) -> String {
+    let base = note_name(&video_info.title);
+    let key = video_key(&video_info.url);
+    let candidates = std::iter::once(base.clone())
+        .chain(youtube_id(&video_info.url).map(|id| format!("{} ({})", base, id)))
+        .chain((2..).map(|n| format!("{} ({})", base, n)));
+    for name in candidates {
+        let owner = match tokio::fs::read_to_string(notes_dir.join(format!("{}.md", name))).await {
+            Ok(note) => front_matter_url(&note).map(|url| video_key(&url)),
+            Err(_) => return name,
+        };
+        if owner.as_deref() == Some(key.as_str()) {
+            return name;
+        }
+    }
+    unreachable!("the numbered candidates never run out")
+}
+
+/// Vault-relative link target of a note, e.g. `Canales/Mi canal`.
+fn link_target(folder: &str, name: &str) -> String {
+    let folder = folder.trim().trim_matches('/');
+    if folder.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", folder, name)
+    }
+}
+
+/// `[[target|alias]]`, keeping the alias from breaking the link syntax.
+fn wikilink(target: &str, alias: &str) -> String {
+    let alias: String = alias
+        .chars()
+        .map(|c| if matches!(c, '|' | '[' | ']') { ' ' } else { c })
+        .collect();
+    let alias = alias.split_whitespace().collect::<Vec<_>>().join(" ");
+    format!("[[{}|{}]]", target, alias)
+}
+
+/// Obsidian tags allow letters, digits, `_`, `-` and `/`, and may not be
+/// purely numeric.
+fn obsidian_tag(tag: &str) -> Option<String> {
+    let tag: String = tag
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .chars()
+        .filter(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '/'))
+        .collect();
+    (!tag.is_empty() && !tag.chars().all(|c| c.is_ascii_digit())).then_some(tag)
+}
+
+/// JSON strings are valid YAML scalars and take care of quoting.
+fn yaml(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+async fn write_file(path: &Path, content: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Error creando la carpeta {}: {}", parent.display(), e))?;
+    }
+    tokio::fs::write(path, content)
+        .await
+        .map_err(|e| format!("Error guardando {}: {}", path.display(), e))
+}
+
+/// Download the thumbnail next to the other attachments, returning its file name.
+async fn download_thumbnail(url: &str, dir: &Path, name: &str) -> Result<String, String> {
+    let response = reqwest::get(url)
+        .await
+        .map_err(|e| format!("Error descargando la miniatura: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "Error descargando la miniatura ({})",
+            response.status()
+        ));
+    }
+    let extension = match response
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+    {
+        Some("image/webp") => "webp",
+        Some("image/png") => "png",
+        _ => "jpg",
+    };
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| format!("Error descargando la miniatura: {}", e))?;
+
+    let file_name = format!("{}.{}", name, extension);
+    tokio::fs::create_dir_all(dir)
+        .await
+        .map_err(|e| format!("Error creando la carpeta {}: {}", dir.display(), e))?;
+    tokio::fs::write(dir.join(&file_name), &bytes)
+        .await
+        .map_err(|e| format!("Error guardando la miniatura: {}", e))?;
+    Ok(file_name)
+}
+
+/// Add the note to its channel's index, creating the index on first use.
+async fn update_channel_index(
+    vault: &Path,
+    options: &ObsidianExport,
+    video_info: &VideoInfo,
+    note_link: &str,
+) -> Result<(), String> {
+    let path = vault
+        .join(options.channels_folder.trim().trim_matches('/'))
+        .join(format!("{}.md", note_name(&video_info.channel)));
+    let lock = file_lock(&path);
+    let _guard = lock.lock().await;
+
+    let mut content = match tokio::fs::read_to_string(&path).await {
+        Ok(content) => content,
+        Err(_) => format!(
+            "---\ntype: canal\nchannel: {}\n---\n\n# {}\n\n",
+            yaml(&video_info.channel),
+            video_info.channel
+        ),
+    };
+    // Reprocessing a video must not list it twice.
+    if content.contains(&format!("[[{}|", note_link)) {
+        return Ok(());
+    }
+
+    if !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str(&format!("- {}", wikilink(note_link, &video_info.title)));
+    if let Some(date) = &video_info.upload_date {
+        content.push_str(&format!(" ({})", date));
+    }
+    content.push('\n');
+    write_file(&path, &content).await
+}
+
+// ─── Export ────────────────────────────────────────────────────────────────────
+
+/// Write the video note, its transcript and thumbnail, and update the
+/// channel index. Returns the path of the note.
+pub(crate) async fn write_obsidian_note(
+    video_info: &VideoInfo,
+    summary: &SummaryResult,
+    transcript: &Transcript,
+    settings: &ProcessSettings,
+) -> Result<String, String> {
+    let options = settings.obsidian_export.clone().unwrap_or_default();
+    let configured = |dir: &Option<String>| dir.clone().filter(|d| !d.trim().is_empty());
+    let vault = match configured(&options.vault_dir).or_else(|| configured(&settings.output_dir)) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(get_default_output_dir().await?),
+    };
+
+    // Held until the note is written, so two videos with the same title
+    // cannot both claim the same name.
+    let notes_dir = vault.join(options.notes_folder.trim().trim_matches('/'));
+    let lock = file_lock(&notes_dir.join(format!("{}.md", note_name(&video_info.title))));
+    let _guard = lock.lock().await;
+    let name = unique_note_name(&notes_dir, video_info).await;
+    let note_link = link_target(&options.notes_folder, &name);
+    let channel_link = link_target(&options.channels_folder, &note_name(&video_info.channel));
+    let transcript_name = format!("{} (transcripción)", name);
+    let transcript_link = link_target(&options.transcripts_folder, &transcript_name);
+
+    let tags = video_tags(
+        &summary.summary,
+        summary.structured.as_ref(),
+        &video_info.title,
+        &settings.tag_dictionary,
+    );
+    let category = classify_offline(
+        &settings.category_taxonomy.clone().unwrap_or_default(),
+        &video_info.title,
+        &summary.summary,
+        summary.structured.as_ref(),
+    )
+    .category;
+
+    // A failed download falls back to the remote image.
+    let thumbnail = match video_info.thumbnail.as_deref().filter(|t| !t.is_empty()) {
+        Some(url) if options.download_thumbnail => {
+            let dir = vault.join(options.attachments_folder.trim().trim_matches('/'));
+            match download_thumbnail(url, &dir, &name).await {
+                Ok(file_name) => Some(format!(
+                    "![[{}]]",
+                    link_target(&options.attachments_folder, &file_name)
+                )),
+                Err(_) => Some(format!("![]({})", url)),
+            }
+        }
+        Some(url) => Some(format!("![]({})", url)),
+        None => None,
+    };
+
+    let mut front_matter = vec![
+        "---".to_string(),
+        format!("title: {}", yaml(&video_info.title)),
+        format!("channel: {}", yaml(&format!("[[{}]]", channel_link))),
+        format!("url: {}", yaml(&video_info.url)),
+    ];
+    if let Some(date) = &video_info.upload_date {
+        front_matter.push(format!("upload_date: {}", date));
+    }
+    front_matter.push(format!(
+        "duration: {}",
+        yaml(&format_duration(video_info.duration))
+    ));
+    let tags: Vec<String> = tags.iter().filter_map(|t| obsidian_tag(t)).collect();
+    if tags.is_empty() {
+        front_matter.push("tags: []".to_string());
+    } else {
+        front_matter.push("tags:".to_string());
+        front_matter.extend(tags.iter().map(|t| format!("  - {}", yaml(t))));
+    }
+    front_matter.push(format!("category: {}", yaml(&category)));
+    front_matter.push(format!("cost: {:.4}", summary.cost_usd));
+    front_matter.push(format!(
+        "processed_at: {}",
+        chrono::Local::now().to_rfc3339()
+    ));
+    front_matter.push("---".to_string());
+
+    let body = match &summary.structured {
+        Some(fields) => fields.to_markdown(),
+        None => summary.summary.clone(),
+    };
+    let mut note = format!("{}\n\n# {}\n\n", front_matter.join("\n"), video_info.title);
+    if let Some(thumbnail) = thumbnail {
+        note.push_str(&format!("{}\n\n", thumbnail));
+    }
+    note.push_str(&format!(
+        "Canal: {} · [Ver en YouTube]({})\n\n## Resumen\n\n{}\n\n## Transcripción\n\n{}\n",
+        wikilink(&channel_link, &video_info.channel),
+        video_info.url,
+        body,
+        wikilink(&transcript_link, "Transcripción completa")
+    ));
+
+    let transcript_note = format!(
+        "---\nvideo: {}\n---\n\n# {}\n\n{}\n",
+        yaml(&format!("[[{}]]", note_link)),
+        transcript_name,
+        markdown_transcript(
+            &video_info.url,
+            &transcript.text,
+            Some(&transcript.segments)
+        )
+    );
+
+    let note_path = vault.join(format!("{}.md", note_link));
+    write_file(&note_path, &note).await?;
+    write_file(
+        &vault.join(format!("{}.md", transcript_link)),
+        &transcript_note,
+    )
+    .await?;
+    update_channel_index(&vault, &options, video_info, &note_link).await?;
+
+    Ok(note_path.to_string_lossy().to_string())
+}
+
+// ─── Commands ──────────────────────────────────────────────────────────────────
+
+/// Export a processed video as a note in the Obsidian vault configured in
+/// `settings` (or the Markdown output folder).
+#[tauri::command]
+pub async fn save_obsidian_note(
+    video_info: VideoInfo,
+    summary: SummaryResult,
+    transcript: Transcript,
+    settings: ProcessSettings,
+) -> Result<String, String> {
+    write_obsidian_note(&video_info, &summary, &transcript, &settings).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn video(title: &str, url: &str) -> VideoInfo {
+        VideoInfo {
+            title: title.to_string(),
+            channel: "Mi canal".to_string(),
+            duration: 60,
+            url: url.to_string(),
+            thumbnail: None,
+            description: None,
+            upload_date: Some("2024-05-01".to_string()),
+        }
+    }
+
+    /// A vault under the system temp dir, deleted when dropped so a failing
+    /// test leaves nothing behind.
+    struct TempVault(PathBuf);
+
+    impl TempVault {
+        fn new() -> Self {
+            TempVault(std::env::temp_dir().join(format!("vault-{}", uuid::Uuid::new_v4())))
+        }
+    }
+
+    impl std::ops::Deref for TempVault {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempVault {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn channel_index(vault: &Path) -> String {
+        std::fs::read_to_string(vault.join("Canales/Mi canal.md")).unwrap()
+    }
+
+    #[tokio::test]
+    async fn channel_index_is_created_then_appended_without_duplicates() {
+        let vault = TempVault::new();
+        let options = ObsidianExport::default();
+        let first = video("Primero", "https://youtu.be/aaa");
+        let second = video("Segundo", "https://youtu.be/bbb");
+
+        update_channel_index(&vault, &options, &first, "Videos/Primero")
+            .await
+            .unwrap();
+        update_channel_index(&vault, &options, &second, "Videos/Segundo")
+            .await
+            .unwrap();
+        update_channel_index(&vault, &options, &first, "Videos/Primero")
+            .await
+            .unwrap();
+        let index = channel_index(&vault);
+
+        assert!(index.starts_with("---\ntype: canal\nchannel: \"Mi canal\"\n---\n\n# Mi canal\n"));
+        assert!(index.ends_with(
+            "- [[Videos/Primero|Primero]] (2024-05-01)\n\
+             - [[Videos/Segundo|Segundo]] (2024-05-01)\n"
+        ));
+    }
+
+    #[tokio::test]
+    async fn concurrent_channel_updates_keep_every_entry() {
+        let vault = TempVault::new();
+        let tasks: Vec<_> = (0..8)
+            .map(|i| {
+                let vault = vault.to_path_buf();
+                tokio::spawn(async move {
+                    let info = video(&format!("Video {}", i), "https://youtu.be/aaa");
+                    let link = format!("Videos/Video {}", i);
+                    update_channel_index(&vault, &ObsidianExport::default(), &info, &link).await
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+        let index = channel_index(&vault);
+
+        assert_eq!(index.matches("- [[Videos/Video ").count(), 8);
+        assert_eq!(index.matches("type: canal").count(), 1);
+    }
+
+    #[tokio::test]
+    async fn notes_of_different_videos_with_the_same_title_get_distinct_names() {
+        let vault = TempVault::new();
+        let first = video("Receta: pan", "https://youtu.be/aaa");
+        let note = "---\ntitle: \"Receta: pan\"\nurl: \"https://youtu.be/aaa\"\n---\n";
+        write_file(&vault.join("Receta pan.md"), note)
+            .await
+            .unwrap();
+
+        let same = video("Receta: pan", "https://www.youtube.com/watch?v=aaa");
+        let other = video("Receta: pan", "https://youtu.be/bbb");
+        let untracked = video("Receta: pan", "https://example.com/pan");
+        let names = (
+            unique_note_name(&vault, &first).await,
+            unique_note_name(&vault, &same).await,
+            unique_note_name(&vault, &other).await,
+            unique_note_name(&vault, &untracked).await,
+        );
+
+        assert_eq!(names.0, "Receta pan");
+        assert_eq!(names.1, "Receta pan");
+        assert_eq!(names.2, "Receta pan (bbb)");
+        assert_eq!(names.3, "Receta pan (2)");
+    }
+
+    #[test]
+    fn front_matter_url_reads_only_the_front_matter() {
+        assert_eq!(
+            front_matter_url("---\ntitle: \"x\"\nurl: \"https://youtu.be/a\"\n---\n").as_deref(),
+            Some("https://youtu.be/a")
+        );
+        assert_eq!(
+            front_matter_url("# Nota\nurl: \"https://youtu.be/a\"\n"),
+            None
+        );
+        assert_eq!(
+            front_matter_url("---\ntitle: \"x\"\n---\nurl: \"https://a\"\n"),
+            None
+        );
+    }
+}
@@ -0,0 +1,546 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::commands::bullet_list;
+
+// ─── Configuration ─────────────────────────────────────────────────────────────
+
+/// LLM backend used for summaries. `Anthropic` uses the Anthropic API key
+/// stored alongside the other settings.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SummaryProviderConfig {
+    #[default]
+    Anthropic,
+    /// Any server exposing `POST {base_url}/chat/completions`: OpenAI, Ollama
+    /// (`http://localhost:11434/v1`), llama.cpp server, LM Studio…
+    OpenaiCompatible {
+        base_url: String,
+        #[serde(default)]
+        api_key: Option<String>,
+        /// USD per million input tokens; local servers leave it unset (free).
+        #[serde(default)]
+        input_price_per_mtok: Option<f64>,
+        /// USD per million output tokens.
+        #[serde(default)]
+        output_price_per_mtok: Option<f64>,
+    },
+}
+
+/// Text and token usage of a single completion.
+pub struct SummaryReply {
+    pub text: String,
+    /// Input tokens billed at the normal rate (excludes cached context).
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    /// Input tokens written to the prompt cache.
+    pub cache_write_tokens: u32,
+    /// Input tokens served from the prompt cache.
+    pub cache_read_tokens: u32,
+}
+
+impl SummaryReply {
+    /// Every input token of the request, cached or not.
+    pub fn total_input_tokens(&self) -> u32 {
+        self.input_tokens + self.cache_write_tokens + self.cache_read_tokens
+    }
+}
+
+/// Summary returned as typed fields instead of free-form Markdown, so
+/// exporters no longer depend on the wording of section headers.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct StructuredSummary {
+    pub central_idea: String,
+    pub key_points: Vec<String>,
+    pub action_items: Vec<String>,
+    pub highlight_quote: String,
+    pub application_context: String,
+    pub keywords: Vec<String>,
+    pub category: String,
+}
+
+impl StructuredSummary {
+    /// JSON schema requested from the model (tool input / response format),
+    /// with `category` restricted to `categories`.
+    pub fn schema(categories: &[String]) -> serde_json::Value {
+        let list = |description: &str| {
+            serde_json::json!({
+                "type": "array",
+                "items": { "type": "string" },
+                "description": description
+            })
+        };
+        serde_json::json!({
+            "type": "object",
+            "additionalProperties": false,
+            "required": [
+                "central_idea", "key_points", "action_items", "highlight_quote",
+                "application_context", "keywords", "category"
+            ],
+            "properties": {
+                "central_idea": {
+                    "type": "string",
+                    "description": "Una sola frase que capture la esencia del video."
+                },
+                "key_points": list("Entre 3 y 7 puntos clave, de máximo 2 líneas cada uno."),
+                "action_items": list("Acciones concretas que el espectador puede aplicar."),
+                "highlight_quote": {
+                    "type": "string",
+                    "description": "Cita textual memorable del video, sin comillas. Vacía si no hay ninguna."
+                },
+                "application_context": {
+                    "type": "string",
+                    "description": "En 2-3 líneas, quién se beneficia de este contenido y en qué situaciones aplicarlo."
+                },
+                "keywords": list("Entre 3 y 8 palabras clave del contenido."),
+                "category": { "type": "string", "enum": categories }
+            }
+        })
+    }
+
+    /// Render the fields with the section layout of the default prompt.
+    pub fn to_markdown(&self) -> String {
+        let mut sections = vec![
+            format!("## 🎯 Idea Central\n{}", self.central_idea),
+            format!("## 📌 Puntos Clave\n{}", bullet_list(&self.key_points)),
+        ];
+        if !self.action_items.is_empty() {
+            sections.push(format!(
+                "## 💡 Ideas Accionables\n{}",
+                bullet_list(&self.action_items)
+            ));
+        }
+        if !self.highlight_quote.trim().is_empty() {
+            sections.push(format!("## 🔑 Cita Destacada\n> \"{}\"", self.highlight_quote.trim()));
+        }
+        if !self.application_context.trim().is_empty() {
+            sections.push(format!("## 📊 Contextos de Aplicación\n{}", self.application_context));
+        }
+        if !self.keywords.is_empty() {
+            sections.push(format!("## 🏷 Keywords\n{}", self.keywords.join(", ")));
+        }
+        sections.push(format!("## 🏷 Categoría\n{}", self.category));
+        sections.join("\n\n")
+    }
+}
+
+/// One turn of a conversation.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChatMessage {
+    pub role: String, // "user" | "assistant"
+    pub content: String,
+}
+
+// ─── Provider Trait ────────────────────────────────────────────────────────────
+
+/// A chat model able to turn a system prompt plus one user message into text.
+#[async_trait]
+pub trait SummaryProvider: Send + Sync {
+    /// Display name used in progress messages and usage records.
+    fn name(&self) -> &str;
+
+    /// Run a single completion with the configured model.
+    async fn complete(
+        &self,
+        system: &str,
+        user_content: &str,
+        max_tokens: u32,
+    ) -> Result<SummaryReply, String>;
+
+    /// Run a completion whose answer is a JSON object matching `schema`.
+    /// `SummaryReply::text` holds that object serialized.
+    async fn complete_structured(
+        &self,
+        system: &str,
+        user_content: &str,
+        schema: &serde_json::Value,
+        max_tokens: u32,
+    ) -> Result<SummaryReply, String>;
+
+    /// Continue a conversation over a large, stable `context` (e.g. a
+    /// transcript). Providers that support it cache `context` between turns.
+    async fn chat(
+        &self,
+        system: &str,
+        context: &str,
+        messages: &[ChatMessage],
+        max_tokens: u32,
+    ) -> Result<SummaryReply, String>;
+
+    /// Cost in USD of the given usage, priced the way this provider bills it.
+    fn cost_usd(&self, input_tokens: u32, output_tokens: u32) -> f64;
+
+    /// Cost in USD of `reply`, including prompt-cache reads and writes.
+    fn reply_cost(&self, reply: &SummaryReply) -> f64 {
+        self.cost_usd(reply.total_input_tokens(), reply.output_tokens)
+    }
+}
+
+/// Build the provider described by `config` for `model`, defaulting to Anthropic.
+pub fn build_summary_provider(
+    config: Option<&SummaryProviderConfig>,
+    anthropic_api_key: &str,
+    model: &str,
+) -> Result<Box<dyn SummaryProvider>, String> {
+    if model.trim().is_empty() {
+        return Err("Selecciona un modelo para generar resúmenes en Ajustes.".to_string());
+    }
+
+    match config.cloned().unwrap_or_default() {
+        SummaryProviderConfig::Anthropic => {
+            if anthropic_api_key.trim().is_empty() {
+                return Err("Falta la API key de Anthropic. Configúrala en Ajustes.".to_string());
+            }
+            Ok(Box::new(AnthropicProvider {
+                api_key: anthropic_api_key.to_string(),
+                model: model.to_string(),
+                client: reqwest::Client::new(),
+            }))
+        }
+        SummaryProviderConfig::OpenaiCompatible {
+            base_url,
+            api_key,
+            input_price_per_mtok,
+            output_price_per_mtok,
+        } => {
+            if base_url.trim().is_empty() {
+                return Err("Configura la URL base del servidor de resúmenes.".to_string());
+            }
+            Ok(Box::new(OpenAiCompatibleProvider {
+                base_url: base_url.trim_end_matches('/').to_string(),
+                api_key: api_key.filter(|k| !k.trim().is_empty()),
+                model: model.to_string(),
+                input_price_per_mtok: input_price_per_mtok.unwrap_or(0.0),
+                output_price_per_mtok: output_price_per_mtok.unwrap_or(0.0),
+                client: reqwest::Client::new(),
+            }))
+        }
+    }
+}
+
+/// Name of the tool (Anthropic) or schema (OpenAI) carrying structured output.
+const STRUCTURED_TOOL_NAME: &str = "structured_summary";
+
+/// USD cost of `tokens` at `price_per_mtok`.
+fn token_cost(tokens: u32, price_per_mtok: f64) -> f64 {
+    tokens as f64 / 1_000_000.0 * price_per_mtok
+}
+
+// ─── Anthropic ─────────────────────────────────────────────────────────────────
+
+pub struct AnthropicProvider {
+    api_key: String,
+    model: String,
+    client: reqwest::Client,
+}
+
+/// USD per million (input, output) tokens of a Claude model.
+fn claude_prices(model: &str) -> (f64, f64) {
+    match model {
+        m if m.contains("claude-opus-4") => (15.0, 75.0),
+        m if m.contains("claude-sonnet-4") => (3.0, 15.0),
+        m if m.contains("claude-haiku-4") => (0.80, 4.0),
+        _ => (3.0, 15.0),
+    }
+}
+
+fn calculate_claude_cost(model: &str, input_tokens: u32, output_tokens: u32) -> f64 {
+    let (input_price, output_price) = claude_prices(model);
+    token_cost(input_tokens, input_price) + token_cost(output_tokens, output_price)
+}
+
+/// Anthropic bills cache writes at 1.25× and cache reads at 0.1× the input price.
+const CLAUDE_CACHE_WRITE_FACTOR: f64 = 1.25;
+const CLAUDE_CACHE_READ_FACTOR: f64 = 0.1;
+
+impl AnthropicProvider {
+    async fn send(&self, request_body: serde_json::Value) -> Result<SummaryReply, String> {
+        let response = self
+            .client
+            .post("https://api.anthropic.com/v1/messages")
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", "2023-06-01")
+            .header("content-type", "application/json")
+            .json(&request_body)
+            .send()
+            .await
+            .map_err(|e| format!("Error conectando con Anthropic: {}", e))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            let msg = if status.as_u16() == 401 {
+                "API key de Anthropic inválida. Verifica tu configuración en Ajustes.".to_string()
+            } else {
+                format!("Error de Anthropic ({}): {}", status, body)
+            };
+            return Err(msg);
+        }
+
+        let json: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| format!("Error parseando respuesta de Anthropic: {}", e))?;
+
+        // A forced tool call answers with its `input` object instead of text.
+        let text = json["content"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find_map(|block| match block["type"].as_str() {
+                Some("text") => block["text"].as_str().map(str::to_string),
+                Some("tool_use") => Some(block["input"].to_string()),
+                _ => None,
+            })
+            .ok_or("Respuesta inesperada de Anthropic")?;
+
+        let usage = &json["usage"];
+        Ok(SummaryReply {
+            text,
+            input_tokens: usage["input_tokens"].as_u64().unwrap_or(0) as u32,
+            output_tokens: usage["output_tokens"].as_u64().unwrap_or(0) as u32,
+            cache_write_tokens: usage["cache_creation_input_tokens"].as_u64().unwrap_or(0) as u32,
+            cache_read_tokens: usage["cache_read_input_tokens"].as_u64().unwrap_or(0) as u32,
+        })
+    }
+}
+
+#[async_trait]
+impl SummaryProvider for AnthropicProvider {
+    fn name(&self) -> &str {
+        "Claude"
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        user_content: &str,
+        max_tokens: u32,
+    ) -> Result<SummaryReply, String> {
+        self.send(serde_json::json!({
+            "model": self.model,
+            "max_tokens": max_tokens,
+            "system": system,
+            "messages": [
+                { "role": "user", "content": user_content }
+            ]
+        }))
+        .await
+    }
+
+    async fn complete_structured(
+        &self,
+        system: &str,
+        user_content: &str,
+        schema: &serde_json::Value,
+        max_tokens: u32,
+    ) -> Result<SummaryReply, String> {
+        self.send(serde_json::json!({
+            "model": self.model,
+            "max_tokens": max_tokens,
+            "system": system,
+            "tools": [{
+                "name": STRUCTURED_TOOL_NAME,
+                "description": "Registra el resumen estructurado del contenido.",
+                "input_schema": schema
+            }],
+            "tool_choice": { "type": "tool", "name": STRUCTURED_TOOL_NAME },
+            "messages": [
+                { "role": "user", "content": user_content }
+            ]
+        }))
+        .await
+    }
+
+    async fn chat(
+        &self,
+        system: &str,
+        context: &str,
+        messages: &[ChatMessage],
+        max_tokens: u32,
+    ) -> Result<SummaryReply, String> {
+        // The context block is marked cacheable so follow-up turns only pay
+        // the cache-read rate for it.
+        self.send(serde_json::json!({
+            "model": self.model,
+            "max_tokens": max_tokens,
+            "system": [
+                { "type": "text", "text": system },
+                {
+                    "type": "text",
+                    "text": context,
+                    "cache_control": { "type": "ephemeral" }
+                }
+            ],
+            "messages": messages
+        }))
+        .await
+    }
+
+    fn cost_usd(&self, input_tokens: u32, output_tokens: u32) -> f64 {
+        calculate_claude_cost(&self.model, input_tokens, output_tokens)
+    }
+
+    fn reply_cost(&self, reply: &SummaryReply) -> f64 {
+        let (input_price, _) = claude_prices(&self.model);
+        calculate_claude_cost(&self.model, reply.input_tokens, reply.output_tokens)
+            + token_cost(reply.cache_write_tokens, input_price * CLAUDE_CACHE_WRITE_FACTOR)
+            + token_cost(reply.cache_read_tokens, input_price * CLAUDE_CACHE_READ_FACTOR)
+    }
+}
+
+// ─── OpenAI-compatible (OpenAI, Ollama, llama.cpp, LM Studio) ──────────────────
+
+pub struct OpenAiCompatibleProvider {
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+    input_price_per_mtok: f64,
+    output_price_per_mtok: f64,
+    client: reqwest::Client,
+}
+
+#[async_trait]
+impl SummaryProvider for OpenAiCompatibleProvider {
+    fn name(&self) -> &str {
+        &self.model
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        user_content: &str,
+        max_tokens: u32,
+    ) -> Result<SummaryReply, String> {
+        self.send(serde_json::json!({
+            "model": self.model,
+            "max_tokens": max_tokens,
+            "messages": [
+                { "role": "system", "content": system },
+                { "role": "user", "content": user_content }
+            ]
+        }))
+        .await
+    }
+
+    async fn complete_structured(
+        &self,
+        system: &str,
+        user_content: &str,
+        schema: &serde_json::Value,
+        max_tokens: u32,
+    ) -> Result<SummaryReply, String> {
+        self.send(serde_json::json!({
+            "model": self.model,
+            "max_tokens": max_tokens,
+            "messages": [
+                { "role": "system", "content": system },
+                { "role": "user", "content": user_content }
+            ],
+            "response_format": {
+                "type": "json_schema",
+                "json_schema": {
+                    "name": STRUCTURED_TOOL_NAME,
+                    "schema": schema,
+                    "strict": true
+                }
+            }
+        }))
+        .await
+    }
+
+    async fn chat(
+        &self,
+        system: &str,
+        context: &str,
+        messages: &[ChatMessage],
+        max_tokens: u32,
+    ) -> Result<SummaryReply, String> {
+        // Servers that cache prompts (OpenAI, llama.cpp) do it by prefix, so
+        // keeping the context first and unchanged is enough.
+        let mut chat = vec![serde_json::json!({
+            "role": "system",
+            "content": format!("{}\n\n{}", system, context)
+        })];
+        chat.extend(messages.iter().map(|m| serde_json::json!(m)));
+        self.send(serde_json::json!({
+            "model": self.model,
+            "max_tokens": max_tokens,
+            "messages": chat
+        }))
+        .await
+    }
+
+    fn cost_usd(&self, input_tokens: u32, output_tokens: u32) -> f64 {
+        token_cost(input_tokens, self.input_price_per_mtok)
+            + token_cost(output_tokens, self.output_price_per_mtok)
+    }
+}
+
+impl OpenAiCompatibleProvider {
+    async fn send(&self, request_body: serde_json::Value) -> Result<SummaryReply, String> {
+        let mut request = self
+            .client
+            .post(format!("{}/chat/completions", self.base_url))
+            .json(&request_body);
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("Error conectando con {}: {}", self.base_url, e))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            let msg = if status.as_u16() == 401 {
+                "API key del servidor de resúmenes inválida. Verifica tu configuración en Ajustes."
+                    .to_string()
+            } else if status.as_u16() == 404 {
+                format!(
+                    "El servidor no encontró el modelo \"{}\" o la ruta /chat/completions: {}",
+                    self.model, body
+                )
+            } else {
+                format!("Error del servidor de resúmenes ({}): {}", status, body)
+            };
+            return Err(msg);
+        }
+
+        let json: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| format!("Error parseando respuesta del servidor de resúmenes: {}", e))?;
+
+        let text = json["choices"][0]["message"]["content"]
+            .as_str()
+            .ok_or("Respuesta inesperada del servidor de resúmenes")?
+            .to_string();
+
+        Ok(SummaryReply {
+            text,
+            input_tokens: json["usage"]["prompt_tokens"].as_u64().unwrap_or(0) as u32,
+            output_tokens: json["usage"]["completion_tokens"].as_u64().unwrap_or(0) as u32,
+            cache_write_tokens: 0,
+            cache_read_tokens: 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn claude_cost_adds_input_and_output_without_rounding() {
+        // 10k input tokens at $3/M = $0.03, 1k output tokens at $15/M = $0.015.
+        let cost = calculate_claude_cost("claude-sonnet-4-6", 10_000, 1_000);
+        assert!((cost - 0.045).abs() < 1e-12);
+
+        let cost = calculate_claude_cost("claude-haiku-4-5", 1_000, 100);
+        assert!((cost - 0.0012).abs() < 1e-12);
+    }
+}
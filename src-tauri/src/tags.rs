@@ -0,0 +1,179 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::commands::{fold_text, parse_section};
+use crate::summary::StructuredSummary;
+
+/// User-maintained synonyms: each canonical tag with the spellings that map to it,
+/// e.g. `{"Inteligencia artificial": ["IA", "AI", "artificial intelligence"]}`.
+pub type TagDictionary = BTreeMap<String, Vec<String>>;
+
+/// Tags taken from the title when the summary has no keywords.
+const MAX_TITLE_TAGS: usize = 6;
+
+/// Title words too common to be useful as tags.
+const STOPWORDS: &[&str] = &[
+    "como", "cual", "cuando", "desde", "donde", "esta", "este", "esto", "hacer", "hasta", "para",
+    "pero", "porque", "sobre", "todo", "todos", "tras", "una", "unos", "about", "after", "from",
+    "have", "into", "more", "than", "that", "their", "this", "what", "when", "with", "your",
+];
+
+/// Keywords of a video as a list: typed fields, then the summary's Keywords
+/// section, then significant title words.
+pub fn extract_keywords(
+    summary: &str,
+    structured: Option<&StructuredSummary>,
+    title: &str,
+) -> Vec<String> {
+    if let Some(fields) = structured.filter(|f| !f.keywords.is_empty()) {
+        return fields.keywords.clone();
+    }
+
+    let section = parse_section(summary, "Keywords");
+    if !section.is_empty() {
+        return section
+            .split([',', ';', '\n'])
+            .map(str::to_string)
+            .collect();
+    }
+
+    title
+        .split_whitespace()
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()))
+        .filter(|w| w.chars().count() > 3)
+        .filter(|w| !STOPWORDS.contains(&fold_text(w).as_str()))
+        .take(MAX_TITLE_TAGS)
+        .map(str::to_string)
+        .collect()
+}
+
+/// Strip list markers, numbering and hashtags, and collapse whitespace.
+fn clean_tag(keyword: &str) -> String {
+    let tag = keyword
+        .trim()
+        .trim_start_matches(['•', '-', '*', '#', ' '])
+        .trim_end_matches(['.', ';', ' ']);
+    // "1. tag" or "2) tag", but not "3D".
+    let unnumbered = tag.trim_start_matches(|c: char| c.is_ascii_digit());
+    let tag = match unnumbered.strip_prefix(['.', ')']) {
+        Some(rest) if unnumbered.len() < tag.len() => rest,
+        _ => tag,
+    };
+    tag.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Lowercase everything except acronyms such as "IA" or "API".
+fn tag_case(tag: &str) -> String {
+    tag.split(' ')
+        .map(|word| {
+            let letters = word.chars().filter(|c| c.is_alphabetic()).count();
+            if letters >= 2 && word.chars().all(|c| !c.is_lowercase()) {
+                word.to_string()
+            } else {
+                word.to_lowercase()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Normalize keywords into tags: dictionary synonyms become their canonical
+/// tag, the rest are lowercased, and duplicates differing only in case or
+/// accents are merged (keeping the accented spelling).
+pub fn normalize_tags(keywords: &[String], dictionary: &TagDictionary) -> Vec<String> {
+    let mut synonyms: HashMap<String, &str> = HashMap::new();
+    for (canonical, variants) in dictionary {
+        synonyms.insert(fold_text(canonical), canonical);
+        for variant in variants {
+            synonyms.insert(fold_text(variant), canonical);
+        }
+    }
+
+    let mut tags: Vec<(String, String, bool)> = Vec::new();
+    for keyword in keywords {
+        let cleaned = clean_tag(keyword);
+        if cleaned.is_empty() {
+            continue;
+        }
+        let (tag, from_dictionary) = match synonyms.get(&fold_text(&cleaned)) {
+            Some(canonical) => (canonical.to_string(), true),
+            None => (tag_case(&cleaned), false),
+        };
+        let key = fold_text(&tag);
+        match tags.iter_mut().find(|(k, _, _)| *k == key) {
+            Some((_, existing, fixed)) => {
+                if !*fixed && !from_dictionary && !tag.is_ascii() && existing.is_ascii() {
+                    *existing = tag;
+                }
+            }
+            None => tags.push((key, tag, from_dictionary)),
+        }
+    }
+    tags.into_iter().map(|(_, tag, _)| tag).collect()
+}
+
+/// Normalized tags of a video, ready for Notion and front matter.
+pub fn video_tags(
+    summary: &str,
+    structured: Option<&StructuredSummary>,
+    title: &str,
+    dictionary: &TagDictionary,
+) -> Vec<String> {
+    normalize_tags(&extract_keywords(summary, structured, title), dictionary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keywords(items: &[&str]) -> Vec<String> {
+        items.iter().map(|k| k.to_string()).collect()
+    }
+
+    #[test]
+    fn clean_tag_strips_markers_but_keeps_leading_digits_of_words() {
+        assert_eq!(clean_tag("  • #Rust;"), "Rust");
+        assert_eq!(
+            clean_tag("1. aprendizaje   automático."),
+            "aprendizaje automático"
+        );
+        assert_eq!(clean_tag("2) productividad"), "productividad");
+        assert_eq!(clean_tag("3D"), "3D");
+        assert_eq!(clean_tag("10x developer"), "10x developer");
+        assert_eq!(clean_tag(" - "), "");
+    }
+
+    #[test]
+    fn tag_case_lowercases_all_but_acronyms() {
+        assert_eq!(tag_case("Machine Learning"), "machine learning");
+        assert_eq!(tag_case("IA Generativa"), "IA generativa");
+        assert_eq!(tag_case("API REST"), "API REST");
+        assert_eq!(tag_case("A"), "a");
+    }
+
+    #[test]
+    fn normalize_tags_merges_case_and_accent_variants() {
+        let tags = normalize_tags(
+            &keywords(&["Programacion", "programación", "PROGRAMACIÓN", "Rust"]),
+            &TagDictionary::new(),
+        );
+        assert_eq!(tags, ["programación", "rust"]);
+    }
+
+    #[test]
+    fn normalize_tags_applies_dictionary_synonyms() {
+        let dictionary = TagDictionary::from([(
+            "Inteligencia artificial".to_string(),
+            vec!["IA".to_string(), "artificial intelligence".to_string()],
+        )]);
+        let tags = normalize_tags(
+            &keywords(&[
+                "- IA",
+                "Artificial Intelligence",
+                "inteligencia artificial",
+                "1. LLM",
+            ]),
+            &dictionary,
+        );
+        assert_eq!(tags, ["Inteligencia artificial", "LLM"]);
+    }
+}
@@ -0,0 +1,356 @@
+use minijinja::{Environment, UndefinedBehavior, Value};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::commands::{
+    bullet_list, format_duration, format_timestamp, front_matter, markdown_transcript,
+    parse_section, timestamp_url, ProcessSettings, TranscriptSegment, VideoInfo,
+};
+use crate::summary::StructuredSummary;
+
+/// Named templates, by name.
+pub type MarkdownTemplates = BTreeMap<String, String>;
+
+/// Layout of the Markdown export when no template is picked.
+pub const DEFAULT_MARKDOWN_TEMPLATE: &str = concat!(
+    "{{ front_matter }}# {{ video.title }}\n\n",
+    "**Canal:** {{ video.channel }}  \n",
+    "**URL:** {{ video.url }}  \n",
+    "**Duración:** {{ duration }}  \n",
+    "**Procesado:** {{ processed_at }}  \n",
+    "**Categoría:** {{ category }}{% if tags %}  \n",
+    "**Keywords:** {{ tags | join(\", \") }}{% endif %}\n\n",
+    "---\n\n## Resumen\n\n{{ summary }}\n\n---\n\n",
+    "## Transcripción completa\n\n{{ transcript_markdown }}\n",
+);
+
+// ─── Context ───────────────────────────────────────────────────────────────────
+
+/// A transcript segment or paragraph with its link into the video.
+#[derive(Debug, Serialize, Clone)]
+pub struct TemplateSegment {
+    pub start: f64,
+    pub end: f64,
+    /// `mm:ss` or `h:mm:ss`.
+    pub timestamp: String,
+    pub url: String,
+    pub text: String,
+}
+
+impl TemplateSegment {
+    pub fn new(video_url: &str, segment: &TranscriptSegment) -> Self {
+        TemplateSegment {
+            start: segment.start,
+            end: segment.end,
+            timestamp: format_timestamp(segment.start),
+            url: timestamp_url(video_url, segment.start),
+            text: segment.text.clone(),
+        }
+    }
+}
+
+/// Sections of the summary as Markdown, from typed fields when available
+/// and otherwise parsed out of the default prompt's headers.
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct SummarySections {
+    pub central_idea: String,
+    pub key_points: String,
+    pub action_items: String,
+    pub highlight_quote: String,
+    pub application_context: String,
+    pub keywords: String,
+    pub category: String,
+}
+
+impl SummarySections {
+    pub fn new(summary: &str, structured: Option<&StructuredSummary>) -> Self {
+        match structured {
+            Some(fields) => SummarySections {
+                central_idea: fields.central_idea.clone(),
+                key_points: bullet_list(&fields.key_points),
+                action_items: bullet_list(&fields.action_items),
+                highlight_quote: fields.highlight_quote.clone(),
+                application_context: fields.application_context.clone(),
+                keywords: fields.keywords.join(", "),
+                category: fields.category.clone(),
+            },
+            None => SummarySections {
+                central_idea: parse_section(summary, "Idea Central"),
+                key_points: parse_section(summary, "Puntos Clave"),
+                action_items: parse_section(summary, "Ideas Accionables"),
+                highlight_quote: parse_section(summary, "Cita Destacada"),
+                application_context: parse_section(summary, "Contextos de Aplicación"),
+                keywords: parse_section(summary, "Keywords"),
+                category: parse_section(summary, "Categoría"),
+            },
+        }
+    }
+}
+
+/// Everything a Markdown template can use. Empty optional values are left
+/// out rather than rendered as `none`, so `| default('')` and `is defined` work.
+#[derive(Debug, Serialize, Clone)]
+pub struct TemplateContext {
+    /// Every `VideoInfo` field: `video.title`, `video.thumbnail`, `video.upload_date`…
+    pub video: VideoInfo,
+    pub duration: String,
+    /// The full summary as Markdown.
+    pub summary: String,
+    pub sections: SummarySections,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub structured: Option<StructuredSummary>,
+    pub transcript: String,
+    /// One-minute paragraphs, each linked to its moment in the video.
+    pub transcript_markdown: String,
+    pub segments: Vec<TemplateSegment>,
+    pub paragraphs: Vec<TemplateSegment>,
+    pub tags: Vec<String>,
+    pub category: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+    pub processed_at: String,
+    pub processed_at_iso: String,
+    /// YAML block with title, channel, url, date, category and keywords.
+    pub front_matter: String,
+}
+
+impl TemplateContext {
+    /// Example video used to check and preview templates.
+    pub fn sample() -> Self {
+        let video = VideoInfo {
+            title: "Cómo hacer pan de masa madre".to_string(),
+            channel: "Cocina en casa".to_string(),
+            duration: 754,
+            url: "https://www.youtube.com/watch?v=dQw4w9WgXcQ".to_string(),
+            thumbnail: Some("https://i.ytimg.com/vi/dQw4w9WgXcQ/hqdefault.jpg".to_string()),
+            description: Some("Receta paso a paso.".to_string()),
+            upload_date: Some("2024-05-01".to_string()),
+        };
+        let structured = StructuredSummary {
+            central_idea: "El pan de masa madre solo necesita harina, agua, sal y tiempo."
+                .to_string(),
+            key_points: vec![
+                "Alimentar la masa madre la noche anterior".to_string(),
+                "Fermentar en frío para más sabor".to_string(),
+            ],
+            action_items: vec!["Hornear a 250 °C con vapor los primeros 20 minutos".to_string()],
+            highlight_quote: "La paciencia es el ingrediente principal.".to_string(),
+            application_context: "Para quien quiere empezar a hornear en casa.".to_string(),
+            keywords: vec!["pan".to_string(), "masa madre".to_string()],
+            category: "Tutorial".to_string(),
+        };
+        let segments = [
+            TranscriptSegment {
+                start: 0.0,
+                end: 6.5,
+                text: "Hoy vamos a hacer pan de masa madre.".to_string(),
+            },
+            TranscriptSegment {
+                start: 65.0,
+                end: 71.0,
+                text: "Primero mezclamos la harina con el agua.".to_string(),
+            },
+        ];
+        let transcript = segments
+            .iter()
+            .map(|s| s.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let summary = structured.to_markdown();
+        let tags = structured.keywords.clone();
+        let category = structured.category.clone();
+        let transcript_markdown = markdown_transcript(&video.url, &transcript, Some(&segments));
+        let segments: Vec<TemplateSegment> = segments
+            .iter()
+            .map(|s| TemplateSegment::new(&video.url, s))
+            .collect();
+        TemplateContext {
+            duration: format_duration(video.duration),
+            sections: SummarySections::new(&summary, Some(&structured)),
+            summary,
+            transcript_markdown,
+            transcript,
+            paragraphs: segments.clone(),
+            segments,
+            front_matter: front_matter(&video, &category, &tags),
+            tags,
+            category,
+            cost_usd: Some(0.0123),
+            processed_at: "01/05/2024 10:30".to_string(),
+            processed_at_iso: "2024-05-01T10:30:00+02:00".to_string(),
+            structured: Some(structured),
+            video,
+        }
+    }
+}
+
+// ─── Rendering ─────────────────────────────────────────────────────────────────
+
+/// Render `template` (MiniJinja syntax) with `context`. Unknown variables
+/// are an error rather than an empty string.
+pub fn render_markdown(template: &str, context: &TemplateContext) -> Result<String, String> {
+    let mut env = Environment::new();
+    env.set_keep_trailing_newline(true);
+    env.set_undefined_behavior(UndefinedBehavior::Strict);
+    env.add_filter("timestamp", |seconds: f64| format_timestamp(seconds));
+    env.add_filter("yaml", |value: Value| {
+        serde_json::to_string(&value).unwrap_or_default()
+    });
+    env.template_from_str(template)
+        .and_then(|t| t.render(context))
+        .map_err(|e| format!("Error en la plantilla Markdown: {:#}", e))
+}
+
+/// Source of the template picked in `settings`: a named one from
+/// `markdown_templates`, or `None` for the built-in layout.
+pub fn selected_template(settings: &ProcessSettings) -> Result<Option<String>, String> {
+    match settings
+        .markdown_template
+        .as_deref()
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+    {
+        None => Ok(None),
+        Some(name) => settings
+            .markdown_templates
+            .get(name)
+            .cloned()
+            .map(Some)
+            .ok_or_else(|| format!("Plantilla Markdown no encontrada: {}", name)),
+    }
+}
+
+/// Fail before any work is done when the template picked in `settings` does
+/// not render with the sample video.
+pub fn check_selected_template(settings: &ProcessSettings) -> Result<(), String> {
+    match selected_template(settings)? {
+        Some(template) => render_markdown(&template, &TemplateContext::sample()).map(|_| ()),
+        None => Ok(()),
+    }
+}
+
+// ─── Commands ──────────────────────────────────────────────────────────────────
+
+/// The built-in template, as a starting point for custom ones.
+#[tauri::command]
+pub fn get_default_markdown_template() -> String {
+    DEFAULT_MARKDOWN_TEMPLATE.to_string()
+}
+
+/// `template` rendered with an example video, to check it while editing.
+#[tauri::command]
+pub fn preview_markdown_template(template: String) -> Result<String, String> {
+    render_markdown(&template, &TemplateContext::sample())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_settings;
+
+    fn settings(template: Option<&str>, source: &str) -> ProcessSettings {
+        ProcessSettings {
+            markdown_template: template.map(str::to_string),
+            markdown_templates: MarkdownTemplates::from([("Mía".to_string(), source.to_string())]),
+            ..test_settings()
+        }
+    }
+
+    #[test]
+    fn default_template_renders_the_sample_video() {
+        let mut context = TemplateContext::sample();
+        context.summary = "Resumen breve.".to_string();
+        let expected = concat!(
+            "---\n",
+            "title: \"Cómo hacer pan de masa madre\"\n",
+            "channel: \"Cocina en casa\"\n",
+            "url: \"https://www.youtube.com/watch?v=dQw4w9WgXcQ\"\n",
+            "upload_date: \"2024-05-01\"\n",
+            "category: \"Tutorial\"\n",
+            "keywords:\n",
+            "  - \"pan\"\n",
+            "  - \"masa madre\"\n",
+            "---\n\n",
+            "# Cómo hacer pan de masa madre\n\n",
+            "**Canal:** Cocina en casa  \n",
+            "**URL:** https://www.youtube.com/watch?v=dQw4w9WgXcQ  \n",
+            "**Duración:** 12m 34s  \n",
+            "**Procesado:** 01/05/2024 10:30  \n",
+            "**Categoría:** Tutorial  \n",
+            "**Keywords:** pan, masa madre\n\n",
+            "---\n\n## Resumen\n\nResumen breve.\n\n---\n\n",
+            "## Transcripción completa\n\n",
+            "[00:00](https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=0) ",
+            "Hoy vamos a hacer pan de masa madre.\n\n",
+            "[01:05](https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=65) ",
+            "Primero mezclamos la harina con el agua.\n",
+        );
+        let rendered = render_markdown(DEFAULT_MARKDOWN_TEMPLATE, &context).unwrap();
+        assert_eq!(rendered, expected);
+
+        context.tags.clear();
+        let rendered = render_markdown(DEFAULT_MARKDOWN_TEMPLATE, &context).unwrap();
+        assert!(rendered.contains("**Categoría:** Tutorial\n\n---"));
+        assert!(!rendered.contains("**Keywords:**"));
+    }
+
+    #[test]
+    fn missing_optional_values_take_defaults() {
+        let mut context = TemplateContext::sample();
+        context.video.thumbnail = None;
+        context.video.upload_date = None;
+        context.cost_usd = None;
+        context.structured = None;
+        let template = "[{{ video.thumbnail | default('') }}]\
+                        [{{ video.upload_date | default('sin fecha') }}]\
+                        [{{ cost_usd | default(0) }}]\
+                        [{% if structured is defined %}sí{% else %}no{% endif %}]";
+        assert_eq!(
+            render_markdown(template, &context).unwrap(),
+            "[][sin fecha][0][no]"
+        );
+    }
+
+    #[test]
+    fn yaml_filter_quotes_any_value() {
+        let context = TemplateContext::sample();
+        let rendered = render_markdown(
+            "{{ video.title | yaml }} {{ tags | yaml }} {{ video.duration | yaml }}",
+            &context,
+        )
+        .unwrap();
+        assert_eq!(
+            rendered,
+            "\"Cómo hacer pan de masa madre\" [\"pan\",\"masa madre\"] 754"
+        );
+    }
+
+    #[test]
+    fn unknown_variables_are_errors() {
+        let context = TemplateContext::sample();
+        assert!(render_markdown("{{ video.titel }}", &context).is_err());
+        assert!(render_markdown("{{ resumen }}", &context).is_err());
+        assert_eq!(
+            render_markdown("{{ paragraphs[1].timestamp | default('') }}", &context).unwrap(),
+            "01:05"
+        );
+    }
+
+    #[test]
+    fn selected_template_is_checked_before_processing() {
+        assert!(check_selected_template(&settings(None, "{{ nada }}")).is_ok());
+        assert!(check_selected_template(&settings(Some("Mía"), "# {{ video.title }}")).is_ok());
+
+        let broken = check_selected_template(&settings(Some("Mía"), "{{ nada }}"));
+        assert!(broken
+            .unwrap_err()
+            .starts_with("Error en la plantilla Markdown"));
+        let unclosed = check_selected_template(&settings(Some("Mía"), "{% if tags %}"));
+        assert!(unclosed.is_err());
+        let missing = check_selected_template(&settings(Some("Otra"), ""));
+        assert_eq!(
+            missing.unwrap_err(),
+            "Plantilla Markdown no encontrada: Otra"
+        );
+    }
+}
@@ -0,0 +1,403 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tokio::process::Command;
+
+// ─── Configuration ─────────────────────────────────────────────────────────────
+
+/// Transcription backend selected in Ajustes. `Groq` uses the Groq API key
+/// stored alongside the other settings.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TranscriptionProviderConfig {
+    #[default]
+    Groq,
+    /// Any server exposing `POST {base_url}/audio/transcriptions` (OpenAI, a
+    /// self-hosted faster-whisper, LocalAI…).
+    OpenaiCompatible {
+        base_url: String,
+        #[serde(default)]
+        api_key: Option<String>,
+        model: String,
+        /// Upload limit of the server in MB; `None` sends files whole.
+        #[serde(default)]
+        max_upload_mb: Option<u64>,
+        /// USD per hour of audio; self-hosted servers leave it unset (free).
+        #[serde(default)]
+        price_per_hour: Option<f64>,
+    },
+    /// Fully offline transcription with a whisper.cpp `whisper-cli` binary.
+    WhisperCpp {
+        binary_path: String,
+        model_path: String,
+        #[serde(default)]
+        threads: Option<u32>,
+    },
+}
+
+const GROQ_BASE_URL: &str = "https://api.groq.com/openai/v1";
+const GROQ_MODEL: &str = "whisper-large-v3";
+/// Groq's price for whisper-large-v3, in USD per hour of audio.
+const GROQ_PRICE_PER_HOUR: f64 = 0.111;
+/// Groq rejects transcription uploads larger than 25 MB.
+const GROQ_MAX_UPLOAD_BYTES: u64 = 25 * 1_048_576;
+
+// ─── Provider Trait ────────────────────────────────────────────────────────────
+
+/// A speech-to-text backend. Every provider answers in the Whisper API
+/// formats (`text` or `verbose_json`) so chunking and merging stay shared.
+#[async_trait]
+pub trait TranscriptionProvider: Send + Sync {
+    /// Display name used in progress messages and usage records.
+    fn name(&self) -> &str;
+
+    /// Largest file accepted in a single request, `None` when unlimited.
+    fn max_upload_bytes(&self) -> Option<u64>;
+
+    /// Cost in USD of transcribing `audio_seconds` of audio.
+    fn cost_usd(&self, audio_seconds: f64) -> f64;
+
+    /// Transcribe one audio file and return the raw response body.
+    async fn transcribe_file(
+        &self,
+        audio_path: &str,
+        language: Option<&str>,
+        response_format: &str,
+    ) -> Result<String, String>;
+}
+
+/// Build the provider described by `config`, defaulting to Groq.
+pub fn build_transcription_provider(
+    config: Option<&TranscriptionProviderConfig>,
+    groq_api_key: &str,
+) -> Result<Box<dyn TranscriptionProvider>, String> {
+    match config.cloned().unwrap_or_default() {
+        TranscriptionProviderConfig::Groq => {
+            if groq_api_key.trim().is_empty() {
+                return Err("Falta la API key de Groq. Configúrala en Ajustes.".to_string());
+            }
+            Ok(Box::new(OpenAiCompatibleProvider {
+                name: "Groq".to_string(),
+                base_url: GROQ_BASE_URL.to_string(),
+                api_key: Some(groq_api_key.to_string()),
+                model: GROQ_MODEL.to_string(),
+                max_upload_bytes: Some(GROQ_MAX_UPLOAD_BYTES),
+                price_per_hour: GROQ_PRICE_PER_HOUR,
+                client: reqwest::Client::new(),
+            }))
+        }
+        TranscriptionProviderConfig::OpenaiCompatible {
+            base_url,
+            api_key,
+            model,
+            max_upload_mb,
+            price_per_hour,
+        } => {
+            if base_url.trim().is_empty() || model.trim().is_empty() {
+                return Err(
+                    "Configura la URL base y el modelo del servidor de transcripción.".to_string(),
+                );
+            }
+            Ok(Box::new(OpenAiCompatibleProvider {
+                name: "Servidor compatible con OpenAI".to_string(),
+                base_url: base_url.trim_end_matches('/').to_string(),
+                api_key: api_key.filter(|k| !k.trim().is_empty()),
+                model,
+                max_upload_bytes: max_upload_mb.map(|mb| mb * 1_048_576),
+                price_per_hour: price_per_hour.unwrap_or(0.0),
+                client: reqwest::Client::new(),
+            }))
+        }
+        TranscriptionProviderConfig::WhisperCpp {
+            binary_path,
+            model_path,
+            threads,
+        } => {
+            if !PathBuf::from(&model_path).is_file() {
+                return Err(format!(
+                    "No se encontró el modelo de whisper.cpp: {}",
+                    model_path
+                ));
+            }
+            Ok(Box::new(WhisperCppProvider {
+                binary_path,
+                model_path,
+                threads,
+            }))
+        }
+    }
+}
+
+// ─── OpenAI-compatible (Groq, OpenAI, self-hosted) ─────────────────────────────
+
+pub struct OpenAiCompatibleProvider {
+    name: String,
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+    max_upload_bytes: Option<u64>,
+    price_per_hour: f64,
+    client: reqwest::Client,
+}
+
+#[async_trait]
+impl TranscriptionProvider for OpenAiCompatibleProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn max_upload_bytes(&self) -> Option<u64> {
+        self.max_upload_bytes
+    }
+
+    fn cost_usd(&self, audio_seconds: f64) -> f64 {
+        audio_seconds.max(0.0) / 3600.0 * self.price_per_hour
+    }
+
+    async fn transcribe_file(
+        &self,
+        audio_path: &str,
+        language: Option<&str>,
+        response_format: &str,
+    ) -> Result<String, String> {
+        let file_bytes = tokio::fs::read(audio_path)
+            .await
+            .map_err(|e| format!("Error leyendo archivo de audio: {}", e))?;
+
+        let filename = PathBuf::from(audio_path)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("audio.mp3")
+            .to_string();
+
+        let file_part = reqwest::multipart::Part::bytes(file_bytes)
+            .file_name(filename)
+            .mime_str("audio/mpeg")
+            .map_err(|e| format!("Error preparando archivo: {}", e))?;
+
+        let mut form = reqwest::multipart::Form::new()
+            .part("file", file_part)
+            .text("model", self.model.clone())
+            .text("response_format", response_format.to_string());
+
+        if let Some(lang) = language {
+            form = form.text("language", lang.to_string());
+        }
+
+        let mut request = self
+            .client
+            .post(format!("{}/audio/transcriptions", self.base_url))
+            .multipart(form);
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("Error conectando con {}: {}", self.name, e))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            let msg = if status.as_u16() == 401 {
+                format!(
+                    "API key de {} inválida. Verifica tu configuración en Ajustes.",
+                    self.name
+                )
+            } else if status.as_u16() == 413 {
+                format!("El archivo de audio es demasiado grande para {}.", self.name)
+            } else {
+                format!("Error de {} ({}): {}", self.name, status, body)
+            };
+            return Err(msg);
+        }
+
+        let transcript = response
+            .text()
+            .await
+            .map_err(|e| format!("Error leyendo respuesta de {}: {}", self.name, e))?;
+
+        Ok(transcript.trim().to_string())
+    }
+}
+
+// ─── whisper.cpp (offline) ─────────────────────────────────────────────────────
+
+/// Runs whisper.cpp locally; audio never leaves the machine.
+pub struct WhisperCppProvider {
+    binary_path: String,
+    model_path: String,
+    threads: Option<u32>,
+}
+
+#[async_trait]
+impl TranscriptionProvider for WhisperCppProvider {
+    fn name(&self) -> &str {
+        "whisper.cpp (local)"
+    }
+
+    fn max_upload_bytes(&self) -> Option<u64> {
+        None
+    }
+
+    fn cost_usd(&self, _audio_seconds: f64) -> f64 {
+        0.0
+    }
+
+    async fn transcribe_file(
+        &self,
+        audio_path: &str,
+        language: Option<&str>,
+        response_format: &str,
+    ) -> Result<String, String> {
+        // whisper.cpp expects 16 kHz mono WAV.
+        let output_base = format!("{}_whisper", audio_path);
+        let wav_path = format!("{}.wav", output_base);
+        let json_path = format!("{}.json", output_base);
+
+        let status = Command::new("ffmpeg")
+            .args([
+                "-hide_banner",
+                "-loglevel",
+                "error",
+                "-y",
+                "-i",
+                audio_path,
+                "-ar",
+                "16000",
+                "-ac",
+                "1",
+                "-c:a",
+                "pcm_s16le",
+                &wav_path,
+            ])
+            .kill_on_drop(true)
+            .status()
+            .await
+            .map_err(|e| {
+                if e.kind() == std::io::ErrorKind::NotFound {
+                    "ffmpeg no está instalado y es necesario para whisper.cpp. Consulta SETUP.md."
+                        .to_string()
+                } else {
+                    format!("Error ejecutando ffmpeg: {}", e)
+                }
+            })?;
+        if !status.success() {
+            let _ = tokio::fs::remove_file(&wav_path).await;
+            return Err("ffmpeg no pudo convertir el audio para whisper.cpp.".to_string());
+        }
+
+        let mut command = Command::new(&self.binary_path);
+        command.kill_on_drop(true).args([
+            "-m",
+            &self.model_path,
+            "-f",
+            &wav_path,
+            "-l",
+            language.unwrap_or("auto"),
+            "-oj",
+            "-of",
+            &output_base,
+            "-np",
+        ]);
+        if let Some(threads) = self.threads {
+            command.args(["-t", &threads.to_string()]);
+        }
+
+        let output = command.output().await;
+        let _ = tokio::fs::remove_file(&wav_path).await;
+        let output = output.map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                format!("No se encontró el ejecutable de whisper.cpp: {}", self.binary_path)
+            } else {
+                format!("Error ejecutando whisper.cpp: {}", e)
+            }
+        })?;
+
+        if !output.status.success() {
+            let _ = tokio::fs::remove_file(&json_path).await;
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("whisper.cpp falló: {}", stderr.trim()));
+        }
+
+        let raw = tokio::fs::read_to_string(&json_path).await;
+        let _ = tokio::fs::remove_file(&json_path).await;
+        let raw = raw.map_err(|e| format!("Error leyendo salida de whisper.cpp: {}", e))?;
+        let json: serde_json::Value = serde_json::from_str(&raw)
+            .map_err(|e| format!("Error parseando salida de whisper.cpp: {}", e))?;
+
+        Ok(whisper_cpp_to_body(&json, response_format))
+    }
+}
+
+/// Convert whisper.cpp's `-oj` output to the Whisper API `text` or
+/// `verbose_json` body.
+fn whisper_cpp_to_body(json: &serde_json::Value, response_format: &str) -> String {
+    let segments: Vec<serde_json::Value> = json["transcription"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|s| {
+            serde_json::json!({
+                "start": s["offsets"]["from"].as_f64().unwrap_or(0.0) / 1000.0,
+                "end": s["offsets"]["to"].as_f64().unwrap_or(0.0) / 1000.0,
+                "text": s["text"].as_str().unwrap_or("").trim(),
+            })
+        })
+        .collect();
+
+    let text = segments
+        .iter()
+        .filter_map(|s| s["text"].as_str())
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    if response_format == "verbose_json" {
+        serde_json::json!({
+            "language": json["result"]["language"],
+            "text": text,
+            "segments": segments,
+        })
+        .to_string()
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn whisper_cpp_output() -> serde_json::Value {
+        serde_json::json!({
+            "result": { "language": "es" },
+            "transcription": [
+                { "offsets": { "from": 0, "to": 2500 }, "text": " Hola a todos." },
+                { "offsets": { "from": 2500, "to": 3000 }, "text": "  " },
+                { "offsets": { "from": 3000, "to": 6200 }, "text": " Empezamos." }
+            ]
+        })
+    }
+
+    #[test]
+    fn whisper_cpp_to_body_text() {
+        assert_eq!(
+            whisper_cpp_to_body(&whisper_cpp_output(), "text"),
+            "Hola a todos. Empezamos."
+        );
+    }
+
+    #[test]
+    fn whisper_cpp_to_body_verbose_json() {
+        let body = whisper_cpp_to_body(&whisper_cpp_output(), "verbose_json");
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["language"], "es");
+        assert_eq!(json["text"], "Hola a todos. Empezamos.");
+        assert_eq!(json["segments"].as_array().unwrap().len(), 3);
+        assert_eq!(json["segments"][0]["end"], 2.5);
+        assert_eq!(json["segments"][2]["start"], 3.0);
+        assert_eq!(json["segments"][2]["text"], "Empezamos.");
+    }
+}
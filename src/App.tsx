@@ -1,6 +1,7 @@
 import { useEffect } from "react";
 import { BookOpen, BarChart3, Settings, FileText } from "lucide-react";
 import { useAppStore } from "./store/useAppStore";
+import { resumeJobs } from "./services/tauri";
 import MainScreen from "./components/MainScreen";
 import ResultView from "./components/ResultView";
 import Dashboard from "./components/Dashboard";
@@ -21,7 +22,10 @@ export default function App() {
     useAppStore();
 
   useEffect(() => {
-    loadSettings();
+    // Unfinished jobs need the API keys, so they resume once settings are loaded.
+    loadSettings()
+      .then(() => resumeJobs(useAppStore.getState().settings))
+      .catch((e) => console.warn("Could not resume jobs:", e));
     loadUsageHistory();
   }, []);
 
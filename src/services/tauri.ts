@@ -11,6 +11,9 @@ import type {
   DepsStatus,
   ProgressEvent,
   AppSettings,
+  ProcessSettings,
+  ProcessStage,
+  Job,
 } from "../types";
 
 // ─── Video Info ───────────────────────────────────────────────────────────────
@@ -105,6 +108,34 @@ export async function checkDependencies(): Promise<DepsStatus> {
   return invoke<DepsStatus>("check_dependencies");
 }
 
+// ─── Job Queue ────────────────────────────────────────────────────────────────
+
+export function toProcessSettings(settings: AppSettings): ProcessSettings {
+  return {
+    groq_api_key: settings.groqApiKey,
+    anthropic_api_key: settings.anthropicApiKey,
+    notion_api_key: settings.notionApiKey || null,
+    notion_parent_id: settings.notionParentId || null,
+    output_dir: settings.outputDir || null,
+    summary_model: settings.summaryModel,
+    custom_prompt: settings.customPrompt,
+    save_locally: settings.saveLocally,
+    send_to_notion: settings.sendToNotion,
+    transcription_language:
+      settings.transcriptionLanguage === "auto" ? null : settings.transcriptionLanguage,
+  };
+}
+
+/** Queue one job per URL; the backend runs them under its stage limits. */
+export async function enqueueJobs(urls: string[], settings: AppSettings): Promise<Job[]> {
+  return invoke<Job[]>("enqueue_jobs", { urls, settings: toProcessSettings(settings) });
+}
+
+/** Restart the jobs left unfinished when the app closed, with the current API keys. */
+export async function resumeJobs(settings: AppSettings): Promise<Job[]> {
+  return invoke<Job[]>("resume_jobs", { settings: toProcessSettings(settings) });
+}
+
 // ─── Event Listeners ──────────────────────────────────────────────────────────
 
 export async function onProcessProgress(
@@ -113,6 +144,10 @@ export async function onProcessProgress(
   return listen<ProgressEvent>("process-progress", (e) => callback(e.payload));
 }
 
+export async function onJobUpdated(callback: (job: Job) => void): Promise<UnlistenFn> {
+  return listen<Job>("job-updated", (e) => callback(e.payload));
+}
+
 // ─── Full Processing Pipeline ─────────────────────────────────────────────────
 
 export interface PipelineCallbacks {
@@ -127,6 +162,23 @@ export interface PipelineCallbacks {
   onError: (message: string) => void;
 }
 
+/** Backend progress stages, as shown by the stage indicator. */
+const STAGES: Record<string, ProcessStage> = {
+  info: "fetching_info",
+  captions: "downloading",
+  download: "downloading",
+  transcribe: "transcribing",
+  summarize: "summarizing",
+  expand: "summarizing",
+  save: "saving",
+  notion: "saving",
+  library: "saving",
+};
+
+/**
+ * Process `url` as a background job. The backend runs every stage (captions or
+ * download, transcription, summary, exports); this only relays its progress.
+ */
 export async function runPipeline(
   url: string,
   settings: AppSettings,
@@ -134,103 +186,60 @@ export async function runPipeline(
 ): Promise<void> {
   const { onLog, onComplete, onError } = callbacks;
 
-  // Subscribe to Rust progress events
-  const unlisten = await onProcessProgress((event) => {
-    onLog(event.message, event.stage, event.percent);
+  // Events may arrive before enqueue_jobs returns the job id; keep them until then.
+  let jobId: string | null = null;
+  const earlyEvents: ProgressEvent[] = [];
+  const earlyJobs: Job[] = [];
+  let finish: () => void = () => {};
+  const finished = new Promise<void>((resolve) => {
+    finish = () => resolve();
   });
-
-  try {
-    // Step 1: Fetch video metadata
-    onLog("Obteniendo información del video...", "fetching_info");
-    const videoInfo = await getVideoInfo(url);
-    onLog(`Video: "${videoInfo.title}" (${formatDuration(videoInfo.duration)})`, "fetching_info");
-
-    // Step 2: Download audio
-    onLog("Iniciando descarga de audio...", "downloading", 0);
-    const audioPath = await downloadAudio(url);
-    onLog("Audio descargado correctamente.", "downloading", 100);
-
-    // Step 3: Transcribe
-    onLog("Enviando a Groq Whisper para transcripción...", "transcribing");
-    const transcript = await transcribeAudio(
-      audioPath,
-      settings.groqApiKey,
-      settings.transcriptionLanguage
-    );
-    onLog(
-      `Transcripción completada: ${transcript.split(" ").length.toLocaleString()} palabras.`,
-      "transcribing",
-      100
-    );
-
-    // Step 4: Summarize
-    onLog("Generando resumen con Claude...", "summarizing");
-    const summary = await generateSummary(
-      transcript,
-      videoInfo,
-      settings.anthropicApiKey,
-      settings.summaryModel,
-      settings.customPrompt
-    );
-    onLog(
-      `Resumen generado — ${summary.total_tokens.toLocaleString()} tokens (~$${summary.cost_usd.toFixed(4)}).`,
-      "summarizing",
-      100
-    );
-
-    // Step 5: Save locally
-    let savedPath: string | undefined;
-    if (settings.saveLocally && settings.outputDir) {
-      onLog("Guardando archivo Markdown...", "saving");
-      savedPath = await saveMarkdown(
-        videoInfo,
-        summary.summary,
-        transcript,
-        settings.outputDir
-      );
-      onLog(`Guardado en: ${savedPath}`, "saving");
+  let settled = false;
+
+  const logEvent = (event: ProgressEvent) => {
+    // Failures are reported once, from the job's own update.
+    if (event.job_id !== jobId || event.stage === "error") return;
+    onLog(event.message, STAGES[event.stage] ?? event.stage, event.percent);
+  };
+  const handleJob = (job: Job) => {
+    if (job.id !== jobId || settled) return;
+    if (job.status === "completed" && job.result && job.summary) {
+      settled = true;
+      onComplete({
+        videoInfo: job.result.video_info,
+        transcript: job.result.transcript,
+        summary: job.summary,
+        savedPath: job.result.saved_path ?? undefined,
+        notionUrl: job.result.notion_url ?? undefined,
+      });
+      finish();
+    } else if (job.status === "failed" || job.status === "cancelled") {
+      settled = true;
+      onError(job.error ?? "El trabajo se canceló.");
+      finish();
     }
+  };
 
-    // Step 6: Send to Notion
-    let notionUrl: string | undefined;
-    if (settings.sendToNotion) {
-      if (!settings.notionApiKey || !settings.notionParentId) {
-        onLog(
-          "⚠ Notion omitido: falta API key o Database ID en Ajustes.",
-          "saving"
-        );
-      } else {
-        onLog("Enviando a Notion...", "saving");
-        try {
-          notionUrl = await sendToNotion(
-            settings.notionApiKey,
-            settings.notionParentId,
-            videoInfo,
-            summary.summary,
-            transcript
-          );
-          onLog(`✓ Página de Notion creada: ${notionUrl}`, "saving");
-        } catch (notionErr: unknown) {
-          const msg =
-            notionErr instanceof Error ? notionErr.message : String(notionErr);
-          onLog(`⚠ Error enviando a Notion: ${msg}`, "saving");
-        }
-      }
-    }
+  const unlistenProgress = await onProcessProgress((event) => {
+    if (jobId === null) earlyEvents.push(event);
+    else logEvent(event);
+  });
+  const unlistenJobs = await onJobUpdated((job) => {
+    if (jobId === null) earlyJobs.push(job);
+    else handleJob(job);
+  });
 
-    onComplete({ videoInfo, transcript, summary, savedPath, notionUrl });
+  try {
+    const [job] = await enqueueJobs([url], settings);
+    jobId = job.id;
+    earlyEvents.forEach(logEvent);
+    earlyJobs.forEach(handleJob);
+    await finished;
   } catch (e: unknown) {
     const msg = e instanceof Error ? e.message : String(e);
     onError(msg);
   } finally {
-    unlisten();
+    unlistenProgress();
+    unlistenJobs();
   }
 }
-
-function formatDuration(seconds: number): string {
-  const h = Math.floor(seconds / 3600);
-  const m = Math.floor((seconds % 3600) / 60);
-  const s = seconds % 60;
-  if (h > 0) return `${h}h ${m.toString().padStart(2, "0")}m`;
-  return `${m}m ${s.toString().padStart(2, "0")}s`;
-}
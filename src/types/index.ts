@@ -158,4 +158,52 @@ export interface ProgressEvent {
   stage: string;
   message: string;
   percent?: number;
+  job_id?: string;
+}
+
+// ─── Job Queue (from Rust) ────────────────────────────────────────────────────
+
+/** Settings in the shape the Rust commands expect (`ProcessSettings`). */
+export interface ProcessSettings {
+  groq_api_key: string;
+  anthropic_api_key: string;
+  notion_api_key: string | null;
+  notion_parent_id: string | null;
+  output_dir: string | null;
+  summary_model: string;
+  custom_prompt: string;
+  save_locally: boolean;
+  send_to_notion: boolean;
+  transcription_language: string | null;
+}
+
+export type JobStage = "queued" | "downloaded" | "transcribed" | "summarized" | "exported";
+export type JobStatus = "pending" | "running" | "completed" | "failed" | "cancelled";
+
+/** `ProcessResult` as the Rust pipeline returns it. */
+export interface PipelineResult {
+  video_info: VideoInfo;
+  transcript: string;
+  summary: string;
+  tokens_used: number;
+  audio_duration_seconds: number;
+  cost_estimate: number;
+  transcription_cost_usd: number;
+  saved_path: string | null;
+  notion_url: string | null;
+  language: string | null;
+  library_id: string | null;
+}
+
+export interface Job {
+  id: string;
+  url: string;
+  stage: JobStage;
+  status: JobStatus;
+  error: string | null;
+  created_at: string;
+  updated_at: string;
+  video_info: VideoInfo | null;
+  summary: SummaryResult | null;
+  result: PipelineResult | null;
 }